@@ -1,41 +1,69 @@
-#[derive(Debug, Clone, Copy)]
-pub struct Coordinate {
-  x: f32,
-  y: f32,
+use std::ops::{Add, AddAssign, Sub};
+
+use crate::float::{Float, Real};
+use crate::vector::Vector;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinate<T: Float = Real> {
+  x: T,
+  y: T,
+  // Always 0 in the 2D simulation
+  z: T,
 }
 
-impl Coordinate {
-  pub fn new(x: f32, y: f32) -> Self {
-    Coordinate {x, y}
+impl<T: Float> Coordinate<T> {
+  pub fn new(x: T, y: T) -> Self {
+    Coordinate {x, y, z: T::ZERO}
+  }
+
+  pub fn new_3d(x: T, y: T, z: T) -> Self {
+    Coordinate {x, y, z}
   }
 
-  pub fn get_x (&self) -> f32 {
+  pub fn get_x (&self) -> T {
     self.x
   }
 
-  pub fn get_y (&self) -> f32 {
+  pub fn get_y (&self) -> T {
     self.y
   }
 
-  pub fn get_distance_between (&self, coordinate: Self) -> f32 {
-    ((self.x - coordinate.x).powi(2) + (self.y - coordinate.y).powi(2)).sqrt()
+  pub fn get_z (&self) -> T {
+    self.z
+  }
+
+  pub fn get_distance_between (&self, coordinate: Self) -> T {
+    (*self - coordinate).get_norm()
+  }
+}
+
+// Displacement from `rhs` to `self`
+impl<T: Float> Sub for Coordinate<T> {
+  type Output = Vector<T>;
+
+  fn sub(self, rhs: Coordinate<T>) -> Vector<T> {
+    Vector::new_3d(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
   }
+}
+
+impl<T: Float> Add<Vector<T>> for Coordinate<T> {
+  type Output = Coordinate<T>;
 
-  // w.r.t x-axis
-  pub fn get_angle_between (&self, coordinate: Self) -> f32 {
-    ((self.y - coordinate.y) / (self.x - coordinate.x)).atan()
+  fn add(self, rhs: Vector<T>) -> Coordinate<T> {
+    Coordinate::new_3d(self.x + rhs.get_x(), self.y + rhs.get_y(), self.z + rhs.get_z())
   }
+}
 
-  pub fn get_direction(&self, coordinate: Self) -> (f32, f32) {
-    let (mut x, mut y) = (1.0, 1.0);
-    if self.x - coordinate.x < 0.0 {
-      x = -1.0;
-    }
+impl<T: Float> Sub<Vector<T>> for Coordinate<T> {
+  type Output = Coordinate<T>;
 
-    if self.y - coordinate.y < 0.0 {
-      y = -1.0;
-    }
+  fn sub(self, rhs: Vector<T>) -> Coordinate<T> {
+    self + -rhs
+  }
+}
 
-    (x, y)
+impl<T: Float> AddAssign<Vector<T>> for Coordinate<T> {
+  fn add_assign(&mut self, rhs: Vector<T>) {
+    *self = *self + rhs;
   }
-}
\ No newline at end of file
+}
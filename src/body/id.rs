@@ -0,0 +1,28 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+// Identity of a body, unique for the whole run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BodyId(u64);
+
+impl BodyId {
+  // Shared by all tree aggregates, and never given to a body, so that it doesn't match a real one
+  pub const AGGREGATE: BodyId = BodyId(u64::MAX);
+
+  pub fn next() -> Self {
+    BodyId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::BodyId;
+
+  #[test]
+  fn test_ids_are_unique() {
+    let ids: Vec<BodyId> = (0..1000).map(|_| BodyId::next()).collect();
+
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+  }
+}
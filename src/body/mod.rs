@@ -1,85 +1,218 @@
 use amethyst::ecs::prelude::{Component, DenseVecStorage};
 
 pub mod coordinate;
+pub mod id;
+pub mod selection;
+pub mod softening;
 
 use crate::vector::Vector;
 use coordinate::Coordinate;
-use std::time::Instant;
+use id::BodyId;
 
-use crate::system::G;
+use crate::system::{G, SOFTENING};
+use crate::float::{Float, Real};
 
+// Generic over the scalar like the geometry types; gravity between bodies is evaluated in `Real`
 #[derive(Debug, Clone, Copy)]
-pub struct Body {
-  id: Instant,
-  mass: f32,
-  coordinate: Coordinate,
-  velocity: Vector,
+pub struct Body<T: Float = Real> {
+  id: BodyId,
+  mass: T,
+  coordinate: Coordinate<T>,
+  // Coordinate at the start of the last physics step, used to interpolate rendering
+  previous_coordinate: Coordinate<T>,
+  velocity: Vector<T>,
+  // Acceleration at the end of the last physics step, used by `OpeningCriterion::Relative` and the
+  // integrators that start a step from it
+  acceleration: Vector<T>,
+  // Block timestep level at the end of the last physics step, see `BlockLeapfrog`
+  level: u32,
+  radius: T,
 }
 
-impl Body {
-  pub fn new(mass: f32, coordinate: Coordinate) -> Self {
+impl<T: Float> Body<T> {
+  pub fn new(mass: T, coordinate: Coordinate<T>) -> Self {
+    Body::new_with_id(BodyId::next(), mass, coordinate)
+  }
+
+  // Stands for several bodies, such as the centre of mass of a tree node, so it doesn't take an id
+  pub fn new_aggregate(mass: T, coordinate: Coordinate<T>) -> Self {
+    Body::new_with_id(BodyId::AGGREGATE, mass, coordinate)
+  }
+
+  fn new_with_id(id: BodyId, mass: T, coordinate: Coordinate<T>) -> Self {
     Body {
-      id: Instant::now(),
+      id,
       mass,
       coordinate,
-      velocity: Vector::new(0.0, 0.0),
+      previous_coordinate: coordinate,
+      velocity: Vector::new(T::ZERO, T::ZERO),
+      acceleration: Vector::new(T::ZERO, T::ZERO),
+      level: 0,
+      radius: T::ZERO,
     }
   }
 
-  pub fn get_id(&self) -> &Instant {
+  pub fn new_with_radius(mass: T, coordinate: Coordinate<T>, radius: T) -> Self {
+    let mut body = Body::new(mass, coordinate);
+    body.radius = radius;
+    body
+  }
+
+  pub fn get_id(&self) -> &BodyId {
     &self.id
   }
 
-  pub fn get_coordinate(&self) -> &Coordinate {
+  pub fn get_coordinate(&self) -> &Coordinate<T> {
     &self.coordinate
   }
 
-  pub fn get_mass(&self) -> f32 {
+  pub fn get_previous_coordinate(&self) -> &Coordinate<T> {
+    &self.previous_coordinate
+  }
+
+  pub fn store_previous_coordinate(&mut self) {
+    self.previous_coordinate = self.coordinate;
+  }
+
+  pub fn set_previous_coordinate(&mut self, coordinate: Coordinate<T>) {
+    self.previous_coordinate = coordinate;
+  }
+
+  pub fn get_mass(&self) -> T {
     self.mass
   }
 
-  pub fn add_body(&self, body: &Body) -> Self {
+  pub fn get_radius(&self) -> T {
+    self.radius
+  }
+
+  pub fn set_radius(&mut self, radius: T) {
+    self.radius = radius;
+  }
+
+  pub fn get_velocity(&self) -> &Vector<T> {
+    &self.velocity
+  }
+
+  pub fn get_acceleration(&self) -> &Vector<T> {
+    &self.acceleration
+  }
+
+  pub fn set_acceleration(&mut self, acceleration: Vector<T>) {
+    self.acceleration = acceleration;
+  }
+
+  pub fn get_level(&self) -> u32 {
+    self.level
+  }
+
+  pub fn set_level(&mut self, level: u32) {
+    self.level = level;
+  }
+
+  pub fn set_coordinate(&mut self, coordinate: Coordinate<T>) {
+    self.coordinate = coordinate;
+  }
+
+  pub fn set_velocity(&mut self, velocity: Vector<T>) {
+    self.velocity = velocity;
+  }
+
+  pub fn add_body(&self, body: &Body<T>) -> Self {
     let total_mass = self.mass + body.mass;
     let x = (self.get_coordinate().get_x() * self.mass + body.get_coordinate().get_x() * body.mass)
       / total_mass;
     let y = (self.get_coordinate().get_y() * self.mass + body.get_coordinate().get_y() * body.mass)
       / total_mass;
+    let z = (self.get_coordinate().get_z() * self.mass + body.get_coordinate().get_z() * body.mass)
+      / total_mass;
 
-    Body::new(total_mass, Coordinate::new(x, y))
+    let mut resultant_body = Body::new_aggregate(total_mass, Coordinate::new_3d(x, y, z));
+    // Centre of mass velocity, so that aggregates can report a jerk
+    resultant_body.velocity = (self.velocity * self.mass + body.velocity * body.mass) / total_mass;
+
+    resultant_body
   }
 
-  pub fn get_distance_between(&self, body: &Body) -> f32 {
+  // Inelastic merge of `body` into `self`, which keeps its identity. Mass, momentum and the centre
+  // of mass are conserved; the radius is left to the caller.
+  pub fn absorb(&mut self, body: &Body<T>) {
+    let (mass, total_mass) = (self.mass, self.mass + body.mass);
+    let weighted = |a: &Coordinate<T>, b: &Coordinate<T>| Coordinate::new_3d(
+      (a.get_x() * mass + b.get_x() * body.mass) / total_mass,
+      (a.get_y() * mass + b.get_y() * body.mass) / total_mass,
+      (a.get_z() * mass + b.get_z() * body.mass) / total_mass,
+    );
+
+    self.coordinate = weighted(&self.coordinate, &body.coordinate);
+    self.previous_coordinate = weighted(&self.previous_coordinate, &body.previous_coordinate);
+    self.velocity = (self.velocity * self.mass + body.velocity * body.mass) / total_mass;
+    self.acceleration = (self.acceleration * self.mass + body.acceleration * body.mass) / total_mass;
+    // The finer of the two, until the merged body's next evaluation
+    self.level = self.level.max(body.level);
+    self.mass = total_mass;
+  }
+
+  pub fn get_distance_between(&self, body: &Body<T>) -> T {
     self.coordinate.get_distance_between(body.coordinate)
   }
 
+  pub fn update_velocity(&mut self, acceleration: Vector<T>, time: T) {
+    self.velocity += acceleration * time;
+  }
+
+  pub fn update_coordinate(&mut self, acceleration: Vector<T>, time: T) -> Vector<T> {
+    let delta = self.velocity * time + acceleration * (T::from_f64(0.5) * time.powi(2));
+    self.coordinate += delta;
+
+    delta
+  }
+}
+
+impl Body {
   pub fn calculate_force_on(&self, body: &Body) -> Vector {
+    self.calculate_acceleration_on(body) * body.mass
+  }
+
+  // Gravitational potential energy of the pair
+  pub fn calculate_potential_energy_with(&self, body: &Body) -> Real {
     let distance = self.get_distance_between(body);
-    let angle = self.coordinate.get_angle_between(body.coordinate);
-    let magnitude = (G * self.mass * body.mass) / distance.powi(2);
-    let (x_direction, y_direction) = self.coordinate.get_direction(body.coordinate);
+    if distance == 0.0 {
+      return 0.0;
+    }
 
-    Vector::new_with(magnitude, angle, Vector::new(x_direction, y_direction))
+    -G * self.mass * body.mass * SOFTENING.get_potential_factor(distance)
   }
 
-  pub fn update_velocity(&mut self, acceleration: Vector, time: f32) {
-    self.velocity = Vector::new(
-      self.velocity.get_x() + acceleration.get_x() * time,
-      self.velocity.get_y() + acceleration.get_y() * time,
-    );
+  // Acceleration that `self` induces on `body`, along the displacement between them so that no angle
+  // is ever reconstructed
+  pub fn calculate_acceleration_on(&self, body: &Body) -> Vector {
+    let offset = self.coordinate - body.coordinate;
+    let distance = offset.get_norm();
+    // Coincident bodies have no direction to pull in
+    if distance == 0.0 {
+      return Vector::new(0.0, 0.0);
+    }
+
+    offset * (G * self.mass * SOFTENING.get_force_factor(distance))
   }
 
-  pub fn update_coordinate(&mut self, acceleration: Vector, time: f32) -> Vector {
-    let delta = Vector::new(
-      (self.velocity.get_x() * time) + (0.5 * acceleration.get_x() * time.powi(2)),
-      (self.velocity.get_y() * time) + (0.5 * acceleration.get_y() * time.powi(2))
-    );
+  // Acceleration and its time derivative (jerk) that `self` induces on `body`
+  pub fn calculate_acceleration_and_jerk_on(&self, body: &Body) -> (Vector, Vector) {
+    let offset = self.coordinate - body.coordinate;
+    let relative_velocity = self.velocity - body.velocity;
+    let distance = offset.get_norm();
+    if distance == 0.0 {
+      return (Vector::new(0.0, 0.0), Vector::new(0.0, 0.0));
+    }
 
-    self.coordinate = Coordinate::new(
-      self.coordinate.get_x() + delta.get_x(),
-      self.coordinate.get_y() + delta.get_y()
-    );
+    let factor = SOFTENING.get_force_factor(distance);
+    let rate = offset.dot(&relative_velocity) * SOFTENING.get_force_factor_derivative(distance);
 
-    delta
+    let acceleration = offset * (G * self.mass * factor);
+    let jerk = (relative_velocity * factor + offset * rate) * (G * self.mass);
+
+    (acceleration, jerk)
   }
 }
 
@@ -89,7 +222,7 @@ impl Component for Body {
 
 #[cfg(test)]
 mod tests {
-  use super::{Body, Coordinate};
+  use super::{Body, BodyId, Coordinate, Vector};
   #[test]
   fn test_add_body() {
     let body_a = Body::new(10.0, Coordinate::new(100.0, 100.0));
@@ -100,5 +233,55 @@ mod tests {
     assert_eq!(resultant_body.mass, 20.0);
     assert_eq!(resultant_body.coordinate.get_x(), 150.0);
     assert_eq!(resultant_body.coordinate.get_y(), 150.0);
+    assert_eq!(*resultant_body.get_id(), BodyId::AGGREGATE);
+  }
+
+  #[test]
+  fn test_absorb() {
+    let mut body_a = Body::new(30.0, Coordinate::new(100.0, 100.0));
+    let mut body_b = Body::new(10.0, Coordinate::new(200.0, 100.0));
+    body_a.set_velocity(Vector::new(1.0, 0.0));
+    body_b.set_velocity(Vector::new(-1.0, 4.0));
+    let id = *body_a.get_id();
+
+    body_a.absorb(&body_b);
+
+    assert_eq!(*body_a.get_id(), id);
+    assert_eq!(body_a.get_mass(), 40.0);
+    assert_eq!(body_a.get_coordinate().get_x(), 125.0);
+    assert_eq!(body_a.get_coordinate().get_y(), 100.0);
+    assert_eq!(*body_a.get_velocity(), Vector::new(0.5, 1.0));
+  }
+
+  #[test]
+  fn test_force_points_towards_the_source() {
+    let source = Body::new(10.0, Coordinate::new(100.0, 100.0));
+    let above_left = Body::new(1.0, Coordinate::new(60.0, 130.0));
+    let straight_below = Body::new(1.0, Coordinate::new(100.0, 40.0));
+
+    let force = source.calculate_force_on(&above_left);
+    assert!(force.get_x() > 0.0 && force.get_y() < 0.0);
+    assert!((force.get_x() / force.get_y() + 40.0 / 30.0).abs() < 1e-4);
+
+    let force = source.calculate_force_on(&straight_below);
+    assert_eq!(force.get_x(), 0.0);
+    assert!(force.get_y() > 0.0);
+  }
+
+  #[test]
+  fn test_jerk_is_derivative_of_acceleration() {
+    let source = Body::new(50.0, Coordinate::new(100.0, 120.0));
+    let mut body = Body::new(10.0, Coordinate::new(140.0, 90.0));
+    body.set_velocity(Vector::new(-3.0, 5.0));
+
+    let (acceleration, jerk) = source.calculate_acceleration_and_jerk_on(&body);
+    let time = 1e-2;
+    body.update_coordinate(Vector::new(0.0, 0.0), time);
+    let (next_acceleration, _) = source.calculate_acceleration_and_jerk_on(&body);
+
+    let jerk_x = (next_acceleration.get_x() - acceleration.get_x()) / time;
+    let jerk_y = (next_acceleration.get_y() - acceleration.get_y()) / time;
+    assert!((jerk_x - jerk.get_x()).abs() < 1e-2 * jerk.get_x().abs());
+    assert!((jerk_y - jerk.get_y()).abs() < 1e-2 * jerk.get_y().abs());
   }
 }
@@ -0,0 +1,54 @@
+use super::{id::BodyId, Body};
+
+// The body picked out by its id, such as the one the camera follows. When the selected body is absorbed
+// in a merge the selection moves on to the body that absorbed it.
+#[derive(Debug, Default)]
+pub struct Selection {
+  id: Option<BodyId>,
+}
+
+impl Selection {
+  pub fn select(&mut self, id: BodyId) {
+    self.id = Some(id);
+  }
+
+  // The selected body among `bodies`, None when there is none
+  pub fn find<'a>(&self, mut bodies: impl Iterator<Item = &'a Body>) -> Option<&'a Body> {
+    let id = self.id?;
+    bodies.find(|body| *body.get_id() == id)
+  }
+
+  pub fn on_absorbed(&mut self, absorbed: &BodyId, survivor: &BodyId) {
+    if self.id.as_ref() == Some(absorbed) {
+      self.id = Some(*survivor);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Body, Selection};
+  use crate::body::coordinate::Coordinate;
+
+  #[test]
+  fn test_selection_follows_merges() {
+    let mut bodies = vec![
+      Body::new(10.0, Coordinate::new(100.0, 100.0)),
+      Body::new(20.0, Coordinate::new(110.0, 100.0)),
+      Body::new(30.0, Coordinate::new(120.0, 100.0)),
+    ];
+    let mut selection = Selection::default();
+    selection.select(*bodies[0].get_id());
+
+    let absorbed = bodies.remove(0);
+    bodies[0].absorb(&absorbed);
+    selection.on_absorbed(absorbed.get_id(), bodies[0].get_id());
+    let absorbed = bodies.remove(0);
+    bodies[0].absorb(&absorbed);
+    selection.on_absorbed(absorbed.get_id(), bodies[0].get_id());
+
+    let selected = selection.find(bodies.iter()).unwrap();
+    assert_eq!(selected.get_id(), bodies[0].get_id());
+    assert_eq!(selected.get_mass(), 60.0);
+  }
+}
@@ -0,0 +1,136 @@
+use crate::float::Real;
+
+// Selected through `system::SOFTENING`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kernel {
+  // Plain Newtonian point masses
+  Newtonian,
+  // Force of a Plummer sphere, a = m * r / (r^2 + length^2)^(3/2)
+  Plummer,
+  // Cubic spline density (Monaghan & Lattanzio). Exactly Newtonian beyond 2.8 * length,
+  // which gives the same potential depth at r = 0 as Plummer softening of `length`.
+  CubicSpline,
+}
+
+// Acceleration due to a softened mass m at separation r (the vector from the body to the mass)
+// is G * m * factor(|r|) * r, where factor(r) = 1 / r^3 for Newtonian gravity.
+#[derive(Debug, Clone, Copy)]
+pub struct Softening {
+  kernel: Kernel,
+  length: Real,
+}
+
+impl Softening {
+  pub const fn new(kernel: Kernel, length: Real) -> Self {
+    Softening { kernel, length }
+  }
+
+  pub fn get_force_factor(&self, distance: Real) -> Real {
+    match self.kernel {
+      Kernel::Newtonian => distance.powi(-3),
+      Kernel::Plummer => (distance.powi(2) + self.length.powi(2)).powf(-1.5),
+      Kernel::CubicSpline => {
+        let support = self.get_spline_support();
+        let u = distance / support;
+        if u < 0.5 {
+          (32.0 / 3.0 + u.powi(2) * (32.0 * u - 38.4)) / support.powi(3)
+        } else if u < 1.0 {
+          (64.0 / 3.0 - 48.0 * u + 38.4 * u.powi(2) - 32.0 / 3.0 * u.powi(3) - 1.0 / (15.0 * u.powi(3)))
+            / support.powi(3)
+        } else {
+          distance.powi(-3)
+        }
+      }
+    }
+  }
+
+  // d(factor)/dr divided by r, which is what the jerk needs
+  pub fn get_force_factor_derivative(&self, distance: Real) -> Real {
+    match self.kernel {
+      Kernel::Newtonian => -3.0 * distance.powi(-5),
+      Kernel::Plummer => -3.0 * (distance.powi(2) + self.length.powi(2)).powf(-2.5),
+      Kernel::CubicSpline => {
+        let support = self.get_spline_support();
+        let u = distance / support;
+        if u < 0.5 {
+          (96.0 * u - 76.8) / support.powi(5)
+        } else if u < 1.0 {
+          (-48.0 / u + 76.8 - 32.0 * u + 0.2 / u.powi(5)) / support.powi(5)
+        } else {
+          -3.0 * distance.powi(-5)
+        }
+      }
+    }
+  }
+
+  // Potential energy of two softened masses is -G * m1 * m2 * potential_factor(|r|)
+  pub fn get_potential_factor(&self, distance: Real) -> Real {
+    match self.kernel {
+      Kernel::Newtonian => 1.0 / distance,
+      Kernel::Plummer => (distance.powi(2) + self.length.powi(2)).powf(-0.5),
+      Kernel::CubicSpline => {
+        let support = self.get_spline_support();
+        let u = distance / support;
+        if u < 0.5 {
+          (2.8 - u.powi(2) * (16.0 / 3.0 + u.powi(2) * (6.4 * u - 9.6))) / support
+        } else if u < 1.0 {
+          (3.2 - 1.0 / (15.0 * u) - u.powi(2) * (32.0 / 3.0 + u * (-16.0 + u * (9.6 - 32.0 / 15.0 * u))))
+            / support
+        } else {
+          1.0 / distance
+        }
+      }
+    }
+  }
+
+  fn get_spline_support(&self) -> Real {
+    2.8 * self.length
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Kernel, Softening};
+
+  #[test]
+  fn test_softened_kernels_are_finite_at_zero() {
+    assert!(Softening::new(Kernel::Plummer, 1.0).get_force_factor(0.0).is_finite());
+    assert!(Softening::new(Kernel::CubicSpline, 1.0).get_force_factor(0.0).is_finite());
+  }
+
+  #[test]
+  fn test_kernels_are_newtonian_at_large_distance() {
+    let newtonian = Softening::new(Kernel::Newtonian, 1.0).get_force_factor(100.0);
+    let plummer = Softening::new(Kernel::Plummer, 1.0).get_force_factor(100.0);
+    let spline = Softening::new(Kernel::CubicSpline, 1.0).get_force_factor(100.0);
+
+    assert!((plummer - newtonian).abs() / newtonian < 1e-3);
+    assert_eq!(spline, newtonian);
+
+    let newtonian = Softening::new(Kernel::Newtonian, 1.0).get_potential_factor(100.0);
+    let plummer = Softening::new(Kernel::Plummer, 1.0).get_potential_factor(100.0);
+    let spline = Softening::new(Kernel::CubicSpline, 1.0).get_potential_factor(100.0);
+
+    assert!((plummer - newtonian).abs() / newtonian < 1e-3);
+    assert_eq!(spline, newtonian);
+  }
+
+  #[test]
+  fn test_cubic_spline_is_continuous() {
+    let softening = Softening::new(Kernel::CubicSpline, 1.0);
+    for &distance in &[1.4, 2.8] {
+      let inside = softening.get_force_factor(distance - 1e-4);
+      let outside = softening.get_force_factor(distance + 1e-4);
+      assert!((inside - outside).abs() / outside < 1e-3);
+
+      let inside = softening.get_force_factor_derivative(distance - 1e-4);
+      let outside = softening.get_force_factor_derivative(distance + 1e-4);
+      assert!((inside - outside).abs() / outside.abs() < 1e-3);
+
+      let inside = softening.get_potential_factor(distance - 1e-4);
+      let outside = softening.get_potential_factor(distance + 1e-4);
+      assert!((inside - outside).abs() / outside < 1e-3);
+    }
+  }
+}
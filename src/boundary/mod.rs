@@ -0,0 +1,182 @@
+use crate::body::{coordinate::Coordinate, Body};
+use crate::quad_tree::{periodic::wrap, quadrant::Quadrant};
+use crate::vector::Vector;
+use crate::float::Real;
+
+// What happens to a body that leaves the arena, see `system::BOUNDARY_POLICY`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoundaryPolicy {
+  // The body is deleted from the world
+  Remove,
+  // The body bounces off the wall; a restitution of 1 keeps its speed
+  Reflect { restitution: Real },
+  // The body comes back in through the opposite wall
+  Wrap,
+  // The body stops on the wall, keeping only the velocity along it
+  Clamp,
+}
+
+impl BoundaryPolicy {
+  // Brings `body` back inside `quadrant`, in the plane, and tells whether it was outside. Bodies to
+  // remove are left untouched for the caller to delete.
+  pub fn apply(&self, quadrant: &Quadrant, body: &mut Body) -> bool {
+    if quadrant.contains(body.get_coordinate()) {
+      return false;
+    }
+
+    let (min_x, min_y) = (quadrant.get_x(), quadrant.get_y());
+    let (max_x, max_y) = (min_x + quadrant.get_length(), min_y + quadrant.get_length());
+    let (coordinate, velocity) = (*body.get_coordinate(), *body.get_velocity());
+
+    match *self {
+      BoundaryPolicy::Remove => {}
+      BoundaryPolicy::Reflect { restitution } => {
+        // Mirrors about the wall that was crossed; one that overshoots the whole arena ends on the far wall
+        let reflect = |value: Real, min: Real, max: Real| match value {
+          value if value < min => ((2.0 * min - value).min(max), true),
+          value if value > max => ((2.0 * max - value).max(min), true),
+          value => (value, false),
+        };
+        let (x, is_reflected_x) = reflect(coordinate.get_x(), min_x, max_x);
+        let (y, is_reflected_y) = reflect(coordinate.get_y(), min_y, max_y);
+        let bounce = |component: Real, is_reflected: bool| if is_reflected { -restitution * component } else { component };
+
+        body.set_coordinate(Coordinate::new_3d(x, y, coordinate.get_z()));
+        body.set_velocity(Vector::new_3d(
+          bounce(velocity.get_x(), is_reflected_x),
+          bounce(velocity.get_y(), is_reflected_y),
+          velocity.get_z(),
+        ));
+      }
+      BoundaryPolicy::Wrap => wrap(quadrant, body),
+      BoundaryPolicy::Clamp => {
+        let x = coordinate.get_x().clamp(min_x, max_x);
+        let y = coordinate.get_y().clamp(min_y, max_y);
+        let stop = |component: Real, value: Real, clamped: Real| if value != clamped { 0.0 } else { component };
+
+        body.set_coordinate(Coordinate::new_3d(x, y, coordinate.get_z()));
+        body.set_velocity(Vector::new_3d(
+          stop(velocity.get_x(), coordinate.get_x(), x),
+          stop(velocity.get_y(), coordinate.get_y(), y),
+          velocity.get_z(),
+        ));
+      }
+    }
+
+    true
+  }
+}
+
+// Bodies each policy acted on
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BoundaryCounts {
+  pub removed: u32,
+  pub reflected: u32,
+  pub wrapped: u32,
+  pub clamped: u32,
+}
+
+impl BoundaryCounts {
+  pub fn add(&mut self, policy: BoundaryPolicy) {
+    match policy {
+      BoundaryPolicy::Remove => self.removed += 1,
+      BoundaryPolicy::Reflect { .. } => self.reflected += 1,
+      BoundaryPolicy::Wrap => self.wrapped += 1,
+      BoundaryPolicy::Clamp => self.clamped += 1,
+    }
+  }
+
+  pub fn get_total(&self) -> u32 {
+    self.removed + self.reflected + self.wrapped + self.clamped
+  }
+}
+
+// Resource with what the boundary policy did in the last physics step, and since the start
+#[derive(Debug, Default)]
+pub struct BoundaryReport {
+  pub last_step: BoundaryCounts,
+  pub total: BoundaryCounts,
+}
+
+impl BoundaryReport {
+  pub fn record(&mut self, counts: BoundaryCounts) {
+    self.last_step = counts;
+    self.total.removed += counts.removed;
+    self.total.reflected += counts.reflected;
+    self.total.wrapped += counts.wrapped;
+    self.total.clamped += counts.clamped;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Body, BoundaryPolicy, Coordinate, Quadrant, Vector};
+
+  fn get_escaped_body() -> Body {
+    let mut body = Body::new(10.0, Coordinate::new(105.0, 50.0));
+    body.set_previous_coordinate(Coordinate::new(98.0, 48.0));
+    body.set_velocity(Vector::new(4.0, 2.0));
+    body
+  }
+
+  #[test]
+  fn test_inside_body_is_untouched() {
+    let quadrant = Quadrant::new(0.0, 0.0, 100.0);
+    let mut body = Body::new(10.0, Coordinate::new(50.0, 50.0));
+
+    assert!(!BoundaryPolicy::Wrap.apply(&quadrant, &mut body));
+    assert_eq!(*body.get_coordinate(), Coordinate::new(50.0, 50.0));
+  }
+
+  #[test]
+  fn test_remove_leaves_body_for_the_caller() {
+    let quadrant = Quadrant::new(0.0, 0.0, 100.0);
+    let mut body = get_escaped_body();
+
+    assert!(BoundaryPolicy::Remove.apply(&quadrant, &mut body));
+    assert_eq!(*body.get_coordinate(), Coordinate::new(105.0, 50.0));
+    assert_eq!(*body.get_velocity(), Vector::new(4.0, 2.0));
+  }
+
+  #[test]
+  fn test_reflect() {
+    let quadrant = Quadrant::new(0.0, 0.0, 100.0);
+    let mut body = get_escaped_body();
+
+    assert!(BoundaryPolicy::Reflect { restitution: 0.5 }.apply(&quadrant, &mut body));
+    assert_eq!(*body.get_coordinate(), Coordinate::new(95.0, 50.0));
+    assert_eq!(*body.get_velocity(), Vector::new(-2.0, 2.0));
+  }
+
+  #[test]
+  fn test_reflect_overshooting_the_far_wall() {
+    let quadrant = Quadrant::new(0.0, 0.0, 100.0);
+    let mut body = Body::new(10.0, Coordinate::new(250.0, 50.0));
+    body.set_velocity(Vector::new(40.0, 2.0));
+
+    assert!(BoundaryPolicy::Reflect { restitution: 1.0 }.apply(&quadrant, &mut body));
+    assert_eq!(*body.get_coordinate(), Coordinate::new(0.0, 50.0));
+    assert_eq!(*body.get_velocity(), Vector::new(-40.0, 2.0));
+  }
+
+  #[test]
+  fn test_wrap() {
+    let quadrant = Quadrant::new(0.0, 0.0, 100.0);
+    let mut body = get_escaped_body();
+
+    assert!(BoundaryPolicy::Wrap.apply(&quadrant, &mut body));
+    assert_eq!(*body.get_coordinate(), Coordinate::new(5.0, 50.0));
+    assert_eq!(*body.get_previous_coordinate(), Coordinate::new(-2.0, 48.0));
+  }
+
+  #[test]
+  fn test_clamp() {
+    let quadrant = Quadrant::new(0.0, 0.0, 100.0);
+    let mut body = get_escaped_body();
+
+    assert!(BoundaryPolicy::Clamp.apply(&quadrant, &mut body));
+    assert_eq!(*body.get_coordinate(), Coordinate::new(100.0, 50.0));
+    assert_eq!(*body.get_velocity(), Vector::new(0.0, 2.0));
+  }
+}
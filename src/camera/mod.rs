@@ -0,0 +1,108 @@
+use crate::body::coordinate::Coordinate;
+use crate::float::Real;
+
+// Closest the camera gets to its target, and to anything it draws
+const MIN_DISTANCE: Real = 10.0;
+// Keeps the camera off the poles, where yaw is undefined
+const MAX_PITCH: Real = std::f64::consts::FRAC_PI_2 as Real - 0.01;
+
+// Where a point lands on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+  pub x: Real,
+  pub y: Real,
+  // Along the view direction, larger is further away
+  pub depth: Real,
+  // Perspective size multiplier, 1 at the target
+  pub scale: Real,
+}
+
+// Resource for the 3D mode: a perspective camera circling `target`. At zero yaw and pitch it looks down
+// the z axis, so that the plane z = target.z is drawn exactly as in the 2D mode.
+#[derive(Debug, Clone, Copy)]
+pub struct OrbitCamera {
+  target: Coordinate,
+  yaw: Real,
+  pitch: Real,
+  distance: Real,
+  focal_length: Real,
+}
+
+impl OrbitCamera {
+  pub fn new(target: Coordinate, distance: Real) -> Self {
+    OrbitCamera {
+      target,
+      yaw: 0.0,
+      pitch: 0.0,
+      distance,
+      focal_length: distance,
+    }
+  }
+
+  pub fn set_target(&mut self, target: Coordinate) {
+    self.target = target;
+  }
+
+  pub fn rotate(&mut self, yaw: Real, pitch: Real) {
+    self.yaw += yaw;
+    self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+  }
+
+  // Scales the distance to the target, below 1 moves closer
+  pub fn zoom(&mut self, factor: Real) {
+    self.distance = (self.distance * factor).max(MIN_DISTANCE);
+  }
+
+  // None for points behind, or too close to, the camera
+  pub fn project(&self, coordinate: &Coordinate) -> Option<Projection> {
+    let x = coordinate.get_x() - self.target.get_x();
+    let y = coordinate.get_y() - self.target.get_y();
+    let z = coordinate.get_z() - self.target.get_z();
+
+    // Yaw turns about the screen's vertical axis, then pitch about its horizontal one
+    let (yaw_sin, yaw_cos) = self.yaw.sin_cos();
+    let (x, z) = (x * yaw_cos - z * yaw_sin, x * yaw_sin + z * yaw_cos);
+    let (pitch_sin, pitch_cos) = self.pitch.sin_cos();
+    let (y, z) = (y * pitch_cos - z * pitch_sin, y * pitch_sin + z * pitch_cos);
+
+    let depth = self.distance - z;
+    if depth < MIN_DISTANCE {
+      return None;
+    }
+    let scale = self.focal_length / depth;
+
+    Some(Projection {
+      x: self.target.get_x() + x * scale,
+      y: self.target.get_y() + y * scale,
+      depth,
+      scale,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Coordinate, OrbitCamera, Real};
+
+  #[test]
+  fn test_project_plane_unchanged() {
+    let camera = OrbitCamera::new(Coordinate::new(500.0, 500.0), 1000.0);
+    let projection = camera.project(&Coordinate::new(600.0, 450.0)).unwrap();
+
+    assert_eq!((projection.x, projection.y, projection.scale), (600.0, 450.0, 1.0));
+  }
+
+  #[test]
+  fn test_project_after_rotate() {
+    let mut camera = OrbitCamera::new(Coordinate::new(0.0, 0.0), 1000.0);
+    camera.rotate(std::f64::consts::FRAC_PI_2 as Real, 0.0);
+    // A quarter turn brings a point on the z axis to the side of the screen
+    let projection = camera.project(&Coordinate::new_3d(0.0, 0.0, 100.0)).unwrap();
+
+    assert!((projection.x + 100.0).abs() < 1e-3);
+    assert!((projection.depth - 1000.0).abs() < 1e-3);
+    // And the camera can't see what's behind it
+    camera.zoom(0.01);
+    assert!(camera.project(&Coordinate::new_3d(100.0, 0.0, 0.0)).is_none());
+  }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use crate::body::{id::BodyId, Body};
+use crate::quad_tree::QuadTree;
+use crate::vector::Vector;
+use crate::float::Real;
+
+// What happens to a pair of overlapping bodies, see `system::COLLISION_RESPONSE`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollisionResponse {
+  // Perfectly inelastic, the pair becomes one body
+  Merge,
+  // Impulse based bounce; a restitution of 1 is elastic, 0 is perfectly plastic
+  Bounce { restitution: Real },
+}
+
+// Index pairs (i, j), i < j, of `bodies` that overlap. `quad_tree` is only the broad phase: it may
+// have been built before the bodies last moved, so it is searched with a margin of the largest
+// displacement since then.
+pub fn find_overlapping_pairs(quad_tree: &QuadTree, bodies: &[Body]) -> Vec<(usize, usize)> {
+  let index_by_id: HashMap<BodyId, usize> = bodies
+    .iter()
+    .enumerate()
+    .map(|(i, body)| (*body.get_id(), i))
+    .collect();
+  // The tree only hands back copies of the bodies, so they are matched up by id
+  debug_assert_eq!(index_by_id.len(), bodies.len(), "bodies share an id");
+  let max_radius = bodies.iter().fold(0.0, |acc: Real, body| acc.max(body.get_radius()));
+  let max_displacement = bodies.iter().fold(0.0, |acc: Real, body| {
+    acc.max(body.get_coordinate().get_distance_between(*body.get_previous_coordinate()))
+  });
+
+  let mut pairs = Vec::new();
+  for (i, body) in bodies.iter().enumerate() {
+    let search_radius = body.get_radius() + max_radius + 2.0 * max_displacement;
+
+    for candidate in quad_tree.find_bodies_within(body.get_coordinate(), search_radius) {
+      let j = match index_by_id.get(candidate.get_id()) {
+        Some(&j) if j > i => j,
+        _ => continue,
+      };
+
+      if body.get_distance_between(&bodies[j]) < body.get_radius() + bodies[j].get_radius() {
+        pairs.push((i, j));
+      }
+    }
+  }
+
+  pairs
+}
+
+// Applies the collision impulse between two overlapping bodies and pushes them apart so they no
+// longer overlap. Momentum is conserved; kinetic energy is too when `restitution` is 1.
+pub fn resolve_bounce(a: &mut Body, b: &mut Body, restitution: Real) {
+  let displacement = *b.get_coordinate() - *a.get_coordinate();
+  let distance = displacement.get_norm();
+  let normal = if distance > 0.0 {
+    displacement.normalise()
+  } else {
+    Vector::new(1.0, 0.0)
+  };
+
+  let (mass_a, mass_b) = (a.get_mass(), b.get_mass());
+  let relative_velocity = *b.get_velocity() - *a.get_velocity();
+  let approach_speed = relative_velocity.dot(&normal);
+
+  // Only bodies moving towards each other exchange momentum
+  if approach_speed < 0.0 {
+    let impulse = -(1.0 + restitution) * approach_speed / (1.0 / mass_a + 1.0 / mass_b);
+    a.set_velocity(*a.get_velocity() - normal * (impulse / mass_a));
+    b.set_velocity(*b.get_velocity() + normal * (impulse / mass_b));
+  }
+
+  let penetration = a.get_radius() + b.get_radius() - distance;
+  if penetration > 0.0 {
+    let total_mass = mass_a + mass_b;
+    // The previous coordinates move along, so that rendering interpolates towards the corrected ones
+    let (shift_a, shift_b) = (normal * (penetration * mass_b / total_mass), normal * (penetration * mass_a / total_mass));
+    a.set_coordinate(*a.get_coordinate() - shift_a);
+    a.set_previous_coordinate(*a.get_previous_coordinate() - shift_a);
+    b.set_coordinate(*b.get_coordinate() + shift_b);
+    b.set_previous_coordinate(*b.get_previous_coordinate() + shift_b);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{find_overlapping_pairs, resolve_bounce, Body, QuadTree, Vector};
+  use crate::body::coordinate::Coordinate;
+
+  #[test]
+  fn test_find_overlapping_pairs() {
+    let bodies = vec![
+      Body::new_with_radius(10.0, Coordinate::new(100.0, 100.0), 5.0),
+      Body::new_with_radius(10.0, Coordinate::new(500.0, 500.0), 5.0),
+      Body::new_with_radius(10.0, Coordinate::new(108.0, 100.0), 5.0),
+    ];
+    let mut quad_tree = QuadTree::new(1000.0);
+    bodies.iter().for_each(|body| quad_tree.insert(*body));
+
+    assert_eq!(find_overlapping_pairs(&quad_tree, &bodies), vec![(0, 2)]);
+  }
+
+  #[test]
+  fn test_elastic_bounce() {
+    let mut a = Body::new_with_radius(10.0, Coordinate::new(100.0, 100.0), 5.0);
+    let mut b = Body::new_with_radius(30.0, Coordinate::new(109.0, 100.0), 5.0);
+    a.set_velocity(Vector::new(4.0, 1.0));
+    b.set_velocity(Vector::new(-2.0, 0.0));
+
+    let momentum = |a: &Body, b: &Body| *a.get_velocity() * a.get_mass() + *b.get_velocity() * b.get_mass();
+    let energy = |body: &Body| 0.5 * body.get_mass() * (body.get_velocity().get_x().powi(2) + body.get_velocity().get_y().powi(2));
+    let (initial_momentum, initial_energy) = (momentum(&a, &b), energy(&a) + energy(&b));
+
+    resolve_bounce(&mut a, &mut b, 1.0);
+
+    let final_momentum = momentum(&a, &b);
+    assert!((final_momentum.get_x() - initial_momentum.get_x()).abs() < 1e-4);
+    assert!((final_momentum.get_y() - initial_momentum.get_y()).abs() < 1e-4);
+    assert!((energy(&a) + energy(&b) - initial_energy).abs() < 1e-3);
+    assert!((a.get_velocity().get_x() + 5.0).abs() < 1e-4);
+    assert_eq!(a.get_velocity().get_y(), 1.0);
+    assert!(a.get_distance_between(&b) >= 10.0 - 1e-4);
+    assert_eq!(a.get_previous_coordinate(), a.get_coordinate());
+    assert_eq!(b.get_previous_coordinate(), b.get_coordinate());
+  }
+}
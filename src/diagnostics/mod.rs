@@ -0,0 +1,136 @@
+use crate::body::{coordinate::Coordinate, Body};
+use crate::quad_tree::{QuadTree, opening_criterion::OpeningCriterion};
+use crate::vector::Vector;
+use crate::float::Real;
+
+// Conserved quantities of the whole system at one physics step
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+  pub step: u64,
+  pub kinetic_energy: Real,
+  pub potential_energy: Real,
+  pub momentum: Vector,
+  // About the origin, out of the plane
+  pub angular_momentum: Real,
+  pub centre_of_mass: Coordinate,
+}
+
+impl Sample {
+  // The potential energy comes from a tree walk, so it carries the tree's approximation error
+  pub fn measure(step: u64, bodies: &[Body], quad_tree: &QuadTree, criterion: OpeningCriterion) -> Self {
+    let mass: Real = bodies.iter().map(|body| body.get_mass()).sum();
+    let kinetic_energy = bodies.iter().fold(0.0, |acc, body| {
+      let velocity = body.get_velocity();
+      acc + 0.5 * body.get_mass() * velocity.dot(velocity)
+    });
+    // Every pair is seen from both sides
+    let potential_energy = 0.5 * bodies.iter().fold(0.0, |acc, body| {
+      acc + quad_tree.calculate_potential_energy_of(body, criterion)
+    });
+    let momentum = bodies.iter().fold(Vector::new(0.0, 0.0), |acc, body| {
+      acc + *body.get_velocity() * body.get_mass()
+    });
+    let angular_momentum = bodies.iter().fold(0.0, |acc, body| {
+      let (coordinate, velocity) = (body.get_coordinate(), body.get_velocity());
+      acc + body.get_mass() * (coordinate.get_x() * velocity.get_y() - coordinate.get_y() * velocity.get_x())
+    });
+    let centre_of_mass = bodies.iter().fold((0.0, 0.0), |(x, y), body| {
+      (x + body.get_coordinate().get_x() * body.get_mass(), y + body.get_coordinate().get_y() * body.get_mass())
+    });
+
+    Sample {
+      step,
+      kinetic_energy,
+      potential_energy,
+      momentum,
+      angular_momentum,
+      centre_of_mass: Coordinate::new(centre_of_mass.0 / mass, centre_of_mass.1 / mass),
+    }
+  }
+
+  pub fn get_total_energy(&self) -> Real {
+    self.kinetic_energy + self.potential_energy
+  }
+}
+
+// Resource holding every `Sample` taken so far, one each `interval` physics steps
+#[derive(Debug)]
+pub struct Diagnostics {
+  interval: u64,
+  step: u64,
+  history: Vec<Sample>,
+}
+
+impl Diagnostics {
+  pub fn new(interval: u64) -> Self {
+    Diagnostics {
+      interval,
+      step: 0,
+      history: Vec::new(),
+    }
+  }
+
+  // Counts a physics step and tells whether it should be sampled
+  pub fn next_step(&mut self) -> bool {
+    self.step += 1;
+    self.step % self.interval == 0
+  }
+
+  pub fn get_step(&self) -> u64 {
+    self.step
+  }
+
+  pub fn record(&mut self, sample: Sample) {
+    self.history.push(sample);
+  }
+
+  pub fn get_latest(&self) -> Option<&Sample> {
+    self.history.last()
+  }
+
+  pub fn get_history(&self) -> &Vec<Sample> {
+    &self.history
+  }
+
+  // Relative change in total energy since the first sample
+  pub fn get_energy_error(&self) -> Option<Real> {
+    let first = self.history.first()?.get_total_energy();
+    let latest = self.history.last()?.get_total_energy();
+
+    Some(((latest - first) / first).abs())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Body, Coordinate, Diagnostics, OpeningCriterion, QuadTree, Sample, Vector};
+  use crate::system::G;
+
+  #[test]
+  fn test_measure() {
+    let mut body_a = Body::new(10.0, Coordinate::new(100.0, 100.0));
+    let mut body_b = Body::new(30.0, Coordinate::new(300.0, 100.0));
+    body_a.set_velocity(Vector::new(0.0, 3.0));
+    body_b.set_velocity(Vector::new(0.0, -1.0));
+    let bodies = vec![body_a, body_b];
+    let mut quad_tree = QuadTree::new(1000.0);
+    bodies.iter().for_each(|body| quad_tree.insert(*body));
+
+    let sample = Sample::measure(1, &bodies, &quad_tree, OpeningCriterion::BarnesHut { theta: 0.5 });
+
+    assert_eq!(sample.kinetic_energy, 60.0);
+    assert!((sample.potential_energy - body_a.calculate_potential_energy_with(&body_b)).abs() < 1e-6);
+    assert!((sample.potential_energy + G * 300.0 / 200.0).abs() < 1e-4);
+    assert_eq!(sample.momentum, Vector::new(0.0, 0.0));
+    assert_eq!(sample.angular_momentum, 10.0 * 100.0 * 3.0 - 30.0 * 300.0);
+    assert_eq!(sample.centre_of_mass.get_x(), 250.0);
+  }
+
+  #[test]
+  fn test_next_step() {
+    let mut diagnostics = Diagnostics::new(3);
+    let sampled: Vec<bool> = (0..6).map(|_| diagnostics.next_step()).collect();
+
+    assert_eq!(sampled, vec![false, false, true, false, false, true]);
+  }
+}
@@ -0,0 +1,96 @@
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// Precision of the simulation, f64 with the `f64` cargo feature. Rendering converts to f32 either way.
+#[cfg(not(feature = "f64"))]
+pub type Real = f32;
+#[cfg(feature = "f64")]
+pub type Real = f64;
+
+// Scalar the geometry types are generic over, implemented for f32 and f64
+pub trait Float:
+  Copy
+  + Debug
+  + Default
+  + PartialOrd
+  + Send
+  + Sync
+  + 'static
+  + Add<Output = Self>
+  + Sub<Output = Self>
+  + Mul<Output = Self>
+  + Div<Output = Self>
+  + Neg<Output = Self>
+{
+  const ZERO: Self;
+
+  fn from_f64(value: f64) -> Self;
+  fn sqrt(self) -> Self;
+  fn powi(self, n: i32) -> Self;
+  fn max(self, other: Self) -> Self;
+}
+
+macro_rules! impl_float {
+  ($type:ty) => {
+    impl Float for $type {
+      const ZERO: Self = 0.0;
+
+      fn from_f64(value: f64) -> Self {
+        value as $type
+      }
+
+      fn sqrt(self) -> Self {
+        <$type>::sqrt(self)
+      }
+
+      fn powi(self, n: i32) -> Self {
+        <$type>::powi(self, n)
+      }
+
+      fn max(self, other: Self) -> Self {
+        <$type>::max(self, other)
+      }
+    }
+  };
+}
+
+impl_float!(f32);
+impl_float!(f64);
+
+// Rendering is f32 whatever the simulation runs in
+#[allow(clippy::unnecessary_cast)]
+pub fn to_f32(value: Real) -> f32 {
+  value as f32
+}
+
+// For the parts that always run in double precision
+#[allow(clippy::unnecessary_cast)]
+pub fn to_f64(value: Real) -> f64 {
+  value as f64
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::body::{coordinate::Coordinate, Body};
+  use crate::quad_tree::quadrant::Quadrant;
+  use crate::vector::Vector;
+
+  #[test]
+  fn test_geometry_in_double_precision() {
+    // A millimetre at a million kilometres is lost in f32
+    let quadrant: Quadrant<f64> = Quadrant::new(0.0, 0.0, 1e9);
+    let coordinate = Coordinate::new(1e9 - 1e-3, 0.5);
+
+    assert!(quadrant.contains(&coordinate));
+    assert!((coordinate.get_distance_between(Coordinate::new(1e9, 0.5)) - 1e-3).abs() < 1e-6);
+  }
+
+  #[test]
+  fn test_body_moves_in_double_precision() {
+    let mut body: Body<f64> = Body::new(1.0, Coordinate::new(1e9, 0.0));
+    body.set_velocity(Vector::new(1e-3, 0.0));
+
+    body.update_coordinate(Vector::new(0.0, 0.0), 1.0);
+    assert_eq!(body.get_coordinate().get_x(), 1e9 + 1e-3);
+  }
+}
@@ -0,0 +1,155 @@
+use super::{Field, Integrator};
+use crate::body::Body;
+use crate::vector::Vector;
+use crate::float::Real;
+
+// Kick-drift-kick leapfrog with individual power-of-two block timesteps. A body on level `l`
+// advances with `time / 2^l`; the step is split into `2^max_level` substeps, all bodies drift
+// every substep and forces are only recomputed for the bodies that finish their own step. Every body
+// finishes at the end of the step, and its acceleration and level there open the next one.
+pub struct BlockLeapfrog {
+  max_level: u32,
+  eta: Real,
+}
+
+impl BlockLeapfrog {
+  pub fn new(max_level: u32, eta: Real) -> Self {
+    BlockLeapfrog { max_level, eta }
+  }
+
+  // Aarseth-style criterion, dt = eta * |a| / |da/dt|, rounded down to the block hierarchy
+  fn get_level(&self, acceleration: &Vector, jerk: &Vector, time: Real) -> u32 {
+    let jerk = jerk.get_norm();
+    if jerk == 0.0 {
+      return 0;
+    }
+
+    let desired_time_step = self.eta * acceleration.get_norm() / jerk;
+    let level = (time / desired_time_step).log2().ceil();
+    if level.is_nan() || level <= 0.0 {
+      0
+    } else {
+      (level as u32).min(self.max_level)
+    }
+  }
+}
+
+impl Integrator for BlockLeapfrog {
+  fn step(&self, bodies: &mut [Body], field: &mut dyn Field, time: Real) {
+    let substeps = 1u32 << self.max_level;
+    let substep_time = time / substeps as Real;
+    let level_of = |body: &Body| body.get_level().min(self.max_level);
+    // Number of substeps a body on `level` spans
+    let span = |level: u32| 1u32 << (self.max_level - level);
+    let level_time = |level: u32| time / (1u32 << level) as Real;
+
+    field.invalidate();
+    for substep in 0..substeps {
+      for body in bodies.iter_mut() {
+        let level = level_of(body);
+        if substep % span(level) == 0 {
+          body.update_velocity(*body.get_acceleration(), 0.5 * level_time(level));
+        }
+        body.update_coordinate(Vector::new(0.0, 0.0), substep_time);
+      }
+
+      let active: Vec<usize> = (0..bodies.len())
+        .filter(|&i| (substep + 1) % span(level_of(&bodies[i])) == 0)
+        .collect();
+      if active.is_empty() {
+        continue;
+      }
+
+      let derivatives = field.accelerations_and_jerks_of(bodies, &active);
+      for (i, (acceleration, jerk)) in active.into_iter().zip(derivatives) {
+        let body = &mut bodies[i];
+        let current = level_of(body);
+        body.update_velocity(acceleration, 0.5 * level_time(current));
+        body.set_acceleration(acceleration);
+
+        // Moving to a finer level is always possible here, a coarser one only where its blocks line up
+        let level = self.get_level(&acceleration, &jerk, time);
+        body.set_level(if level >= current {
+          level
+        } else if (substep + 1) % span(current - 1) == 0 {
+          current - 1
+        } else {
+          current
+        });
+      }
+    }
+  }
+
+  fn reuses_accelerations(&self) -> bool {
+    true
+  }
+
+  fn prime(&self, bodies: &mut [Body], field: &mut dyn Field, time: Real) {
+    let derivatives = field.accelerations_and_jerks(bodies);
+    for (body, (acceleration, jerk)) in bodies.iter_mut().zip(derivatives) {
+      body.set_acceleration(acceleration);
+      body.set_level(self.get_level(&acceleration, &jerk, time));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{BlockLeapfrog, Body, Field, Integrator, Vector};
+  use crate::body::coordinate::Coordinate;
+
+  // Unit harmonic oscillator, a = -x, counting the bodies it evaluates
+  #[derive(Default)]
+  struct Spring {
+    evaluated: usize,
+  }
+
+  impl Field for Spring {
+    fn accelerations(&mut self, bodies: &[Body]) -> Vec<Vector> {
+      self.evaluated += bodies.len();
+      bodies
+        .iter()
+        .map(|body| Vector::new(-body.get_coordinate().get_x(), -body.get_coordinate().get_y()))
+        .collect()
+    }
+
+    fn accelerations_and_jerks_of(&mut self, bodies: &[Body], active: &[usize]) -> Vec<(Vector, Vector)> {
+      self.evaluated += active.len();
+      active
+        .iter()
+        .map(|&i| {
+          let coordinate = bodies[i].get_coordinate();
+          (Vector::new(-coordinate.get_x(), -coordinate.get_y()), *bodies[i].get_velocity() * -1.0)
+        })
+        .collect()
+    }
+  }
+
+  #[test]
+  fn test_get_level() {
+    let integrator = BlockLeapfrog::new(4, 0.1);
+    let acceleration = Vector::new(1.0, 0.0);
+
+    assert_eq!(integrator.get_level(&acceleration, &Vector::new(0.0, 0.0), 1.0), 0);
+    assert_eq!(integrator.get_level(&acceleration, &Vector::new(0.0, 0.05), 1.0), 0);
+    assert_eq!(integrator.get_level(&acceleration, &Vector::new(0.0, 0.4), 1.0), 2);
+    assert_eq!(integrator.get_level(&acceleration, &Vector::new(0.0, 1000.0), 1.0), 4);
+  }
+
+  #[test]
+  fn test_step_evaluates_only_the_active_bodies() {
+    let integrator = BlockLeapfrog::new(2, 0.1);
+    let mut fast = Body::new(1.0, Coordinate::new(1.0, 0.0));
+    fast.set_velocity(Vector::new(0.0, 50.0));
+    let mut bodies = vec![Body::new(1.0, Coordinate::new(1.0, 0.0)), fast];
+    let mut field = Spring::default();
+
+    integrator.prime(&mut bodies, &mut field, 0.1);
+    assert_eq!((bodies[0].get_level(), bodies[1].get_level()), (0, 2));
+    field.evaluated = 0;
+    integrator.step(&mut bodies, &mut field, 0.1);
+
+    // Once at the end of the step on level 0, on each of the 4 substeps on level 2
+    assert_eq!(field.evaluated, 5);
+  }
+}
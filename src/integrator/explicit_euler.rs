@@ -0,0 +1,16 @@
+use super::{Field, Integrator};
+use crate::body::Body;
+use crate::float::Real;
+
+// x' = x + v * dt + a * dt^2 / 2, v' = v + a * dt
+pub struct ExplicitEuler;
+
+impl Integrator for ExplicitEuler {
+  fn step(&self, bodies: &mut [Body], field: &mut dyn Field, time: Real) {
+    let accelerations = field.accelerations(bodies);
+    for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+      body.update_coordinate(acceleration, time);
+      body.update_velocity(acceleration, time);
+    }
+  }
+}
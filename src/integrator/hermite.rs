@@ -0,0 +1,43 @@
+use super::{Field, Integrator};
+use crate::body::Body;
+use crate::float::Real;
+
+// Fourth order Hermite predictor-corrector (Makino & Aarseth 1992). Uses the acceleration
+// and jerk at the start of the step and at the predicted state.
+pub struct Hermite;
+
+impl Integrator for Hermite {
+  fn step(&self, bodies: &mut [Body], field: &mut dyn Field, time: Real) {
+    let initial_state = bodies.to_vec();
+    let initial_derivatives = field.accelerations_and_jerks(bodies);
+
+    // Predictor: third order Taylor expansion of the position, second order of the velocity
+    for (body, (acceleration, jerk)) in bodies.iter_mut().zip(&initial_derivatives) {
+      let delta = *body.get_velocity() * time
+        + *acceleration * (time.powi(2) / 2.0)
+        + *jerk * (time.powi(3) / 6.0);
+
+      body.set_coordinate(*body.get_coordinate() + delta);
+      body.update_velocity(*acceleration + *jerk * (time / 2.0), time);
+    }
+
+    let predicted_derivatives = field.accelerations_and_jerks(bodies);
+
+    // Corrector
+    for (((body, initial), (acceleration, jerk)), (next_acceleration, next_jerk)) in bodies
+      .iter_mut()
+      .zip(initial_state)
+      .zip(initial_derivatives)
+      .zip(predicted_derivatives)
+    {
+      let velocity = *initial.get_velocity()
+        + (acceleration + next_acceleration) * (time / 2.0)
+        + (jerk - next_jerk) * (time.powi(2) / 12.0);
+      let delta = (*initial.get_velocity() + velocity) * (time / 2.0)
+        + (acceleration - next_acceleration) * (time.powi(2) / 12.0);
+
+      body.set_coordinate(*initial.get_coordinate() + delta);
+      body.set_velocity(velocity);
+    }
+  }
+}
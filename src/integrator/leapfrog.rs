@@ -0,0 +1,28 @@
+use super::{Field, Integrator};
+use crate::body::Body;
+use crate::vector::Vector;
+use crate::float::Real;
+
+// Kick-drift-kick leapfrog. Velocities are synchronised with positions at the end of every step, and
+// the closing kick's acceleration opens the next step, so each step evaluates the field once.
+pub struct Leapfrog;
+
+impl Integrator for Leapfrog {
+  fn step(&self, bodies: &mut [Body], field: &mut dyn Field, time: Real) {
+    for body in bodies.iter_mut() {
+      body.update_velocity(*body.get_acceleration(), 0.5 * time);
+      body.update_coordinate(Vector::new(0.0, 0.0), time);
+    }
+
+    field.invalidate();
+    let accelerations = field.accelerations(bodies);
+    for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+      body.update_velocity(acceleration, 0.5 * time);
+      body.set_acceleration(acceleration);
+    }
+  }
+
+  fn reuses_accelerations(&self) -> bool {
+    true
+  }
+}
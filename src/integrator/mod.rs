@@ -0,0 +1,160 @@
+mod block_leapfrog;
+mod explicit_euler;
+mod hermite;
+mod leapfrog;
+mod runge_kutta4;
+mod semi_implicit_euler;
+mod velocity_verlet;
+
+pub use self::{
+  block_leapfrog::BlockLeapfrog,
+  explicit_euler::ExplicitEuler,
+  hermite::Hermite,
+  leapfrog::Leapfrog,
+  runge_kutta4::RungeKutta4,
+  semi_implicit_euler::SemiImplicitEuler,
+  velocity_verlet::VelocityVerlet,
+};
+
+use crate::body::Body;
+use crate::vector::Vector;
+use crate::float::Real;
+
+// Source of the accelerations an integrator needs. Every call evaluates the
+// acceleration of each body in `bodies` (in the same order) for the state it is given.
+pub trait Field {
+  fn accelerations(&mut self, bodies: &[Body]) -> Vec<Vector>;
+
+  // Acceleration and jerk of each body, for schemes that use the time derivative of the acceleration
+  fn accelerations_and_jerks(&mut self, bodies: &[Body]) -> Vec<(Vector, Vector)> {
+    let all: Vec<usize> = (0..bodies.len()).collect();
+    self.accelerations_and_jerks_of(bodies, &all)
+  }
+
+  // Acceleration and jerk of only the bodies at the `active` indices, due to all of `bodies`
+  fn accelerations_and_jerks_of(&mut self, bodies: &[Body], active: &[usize]) -> Vec<(Vector, Vector)>;
+
+  // The bodies moved before the first evaluation, so it can't use what was prepared for their
+  // state at the start of the step
+  fn invalidate(&mut self) {}
+}
+
+pub trait Integrator: Send + Sync {
+  // Advances every body by `time`, asking `field` for accelerations as often as the scheme needs.
+  fn step(&self, bodies: &mut [Body], field: &mut dyn Field, time: Real);
+
+  // Whether `step` starts from the acceleration each body holds, and leaves the one at the end of the
+  // step there for the next. The first step needs `prime` beforehand.
+  fn reuses_accelerations(&self) -> bool {
+    false
+  }
+
+  // Sets what `step` starts from on bodies that haven't been stepped yet, for steps of `time`
+  fn prime(&self, bodies: &mut [Body], field: &mut dyn Field, _time: Real) {
+    set_accelerations(bodies, field);
+  }
+}
+
+// Sets the acceleration of each body for the state it is in
+pub fn set_accelerations(bodies: &mut [Body], field: &mut dyn Field) {
+  let accelerations = field.accelerations(bodies);
+  for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+    body.set_acceleration(acceleration);
+  }
+}
+
+// Selected at startup, see `system::INTEGRATOR`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IntegratorKind {
+  // Kick-drift-kick leapfrog
+  Leapfrog,
+  // Leapfrog with individual power-of-two timesteps, down to `time_step / 2^max_level`
+  BlockLeapfrog { max_level: u32, eta: Real },
+  VelocityVerlet,
+  // Fourth order schemes for small-N, high accuracy runs
+  RungeKutta4,
+  Hermite,
+  SemiImplicitEuler,
+  // The original update: second order position step followed by an explicit velocity step
+  ExplicitEuler,
+}
+
+impl IntegratorKind {
+  pub fn create(&self) -> Box<dyn Integrator> {
+    match self {
+      IntegratorKind::Leapfrog => Box::new(Leapfrog),
+      IntegratorKind::BlockLeapfrog { max_level, eta } => Box::new(BlockLeapfrog::new(*max_level, *eta)),
+      IntegratorKind::VelocityVerlet => Box::new(VelocityVerlet),
+      IntegratorKind::RungeKutta4 => Box::new(RungeKutta4),
+      IntegratorKind::Hermite => Box::new(Hermite),
+      IntegratorKind::SemiImplicitEuler => Box::new(SemiImplicitEuler),
+      IntegratorKind::ExplicitEuler => Box::new(ExplicitEuler),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Body, Field, IntegratorKind, Real, Vector};
+  use crate::body::coordinate::Coordinate;
+
+  // Unit harmonic oscillator, a = -x
+  struct Spring;
+
+  impl Field for Spring {
+    fn accelerations(&mut self, bodies: &[Body]) -> Vec<Vector> {
+      bodies
+        .iter()
+        .map(|body| Vector::new(-body.get_coordinate().get_x(), -body.get_coordinate().get_y()))
+        .collect()
+    }
+
+    fn accelerations_and_jerks_of(&mut self, bodies: &[Body], active: &[usize]) -> Vec<(Vector, Vector)> {
+      let accelerations = self.accelerations(bodies);
+      active
+        .iter()
+        .map(|&i| (accelerations[i], *bodies[i].get_velocity() * -1.0))
+        .collect()
+    }
+  }
+
+  fn energy(body: &Body) -> Real {
+    let velocity = body.get_velocity();
+    let coordinate = body.get_coordinate();
+    0.5 * (velocity.get_x().powi(2) + velocity.get_y().powi(2))
+      + 0.5 * (coordinate.get_x().powi(2) + coordinate.get_y().powi(2))
+  }
+
+  fn energy_drift(kind: IntegratorKind) -> Real {
+    let integrator = kind.create();
+    let mut bodies = vec![Body::new(1.0, Coordinate::new(1.0, 0.0))];
+    let initial_energy = energy(&bodies[0]);
+    if integrator.reuses_accelerations() {
+      integrator.prime(&mut bodies, &mut Spring, 0.01);
+    }
+
+    (0..10_000).for_each(|_| integrator.step(&mut bodies, &mut Spring, 0.01));
+
+    (energy(&bodies[0]) - initial_energy).abs() / initial_energy
+  }
+
+  #[test]
+  fn test_symplectic_integrators_conserve_energy() {
+    assert!(energy_drift(IntegratorKind::Leapfrog) < 1e-3);
+    assert!(energy_drift(IntegratorKind::VelocityVerlet) < 1e-3);
+    assert!(energy_drift(IntegratorKind::SemiImplicitEuler) < 1e-2);
+    assert!(energy_drift(IntegratorKind::BlockLeapfrog { max_level: 3, eta: 0.1 }) < 1e-3);
+  }
+
+  #[test]
+  fn test_fourth_order_integrators_conserve_energy() {
+    assert!(energy_drift(IntegratorKind::RungeKutta4) < 1e-5);
+    assert!(energy_drift(IntegratorKind::Hermite) < 1e-5);
+  }
+
+  #[test]
+  fn test_explicit_euler_drifts() {
+    assert!(energy_drift(IntegratorKind::ExplicitEuler) > energy_drift(IntegratorKind::Leapfrog));
+  }
+}
@@ -0,0 +1,51 @@
+use super::{Field, Integrator};
+use crate::body::Body;
+use crate::vector::Vector;
+use crate::float::Real;
+
+// Classic fourth order Runge-Kutta over the positions and velocities of the whole system.
+// Needs four field evaluations per step.
+pub struct RungeKutta4;
+
+impl RungeKutta4 {
+  // State at `x + velocity * time`, `v + acceleration * time`
+  fn stage(body: &Body, velocity: Vector, acceleration: Vector, time: Real) -> Body {
+    let mut stage = *body;
+    stage.set_coordinate(*body.get_coordinate() + velocity * time);
+    stage.update_velocity(acceleration, time);
+    stage
+  }
+}
+
+impl Integrator for RungeKutta4 {
+  fn step(&self, bodies: &mut [Body], field: &mut dyn Field, time: Real) {
+    let half_time = 0.5 * time;
+
+    let k1 = field.accelerations(bodies);
+    let stage_2: Vec<Body> = bodies.iter().zip(&k1)
+      .map(|(body, a)| RungeKutta4::stage(body, *body.get_velocity(), *a, half_time))
+      .collect();
+
+    let k2 = field.accelerations(&stage_2);
+    let stage_3: Vec<Body> = bodies.iter().zip(&stage_2).zip(&k2)
+      .map(|((body, stage), a)| RungeKutta4::stage(body, *stage.get_velocity(), *a, half_time))
+      .collect();
+
+    let k3 = field.accelerations(&stage_3);
+    let stage_4: Vec<Body> = bodies.iter().zip(&stage_3).zip(&k3)
+      .map(|((body, stage), a)| RungeKutta4::stage(body, *stage.get_velocity(), *a, time))
+      .collect();
+
+    let k4 = field.accelerations(&stage_4);
+
+    for (i, body) in bodies.iter_mut().enumerate() {
+      let velocity = *body.get_velocity()
+        + (*stage_2[i].get_velocity() + *stage_3[i].get_velocity()) * 2.0
+        + *stage_4[i].get_velocity();
+      let acceleration = k1[i] + (k2[i] + k3[i]) * 2.0 + k4[i];
+
+      body.set_coordinate(*body.get_coordinate() + velocity * (time / 6.0));
+      body.update_velocity(acceleration, time / 6.0);
+    }
+  }
+}
@@ -0,0 +1,17 @@
+use super::{Field, Integrator};
+use crate::body::Body;
+use crate::vector::Vector;
+use crate::float::Real;
+
+// v' = v + a * dt, x' = x + v' * dt
+pub struct SemiImplicitEuler;
+
+impl Integrator for SemiImplicitEuler {
+  fn step(&self, bodies: &mut [Body], field: &mut dyn Field, time: Real) {
+    let accelerations = field.accelerations(bodies);
+    for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+      body.update_velocity(acceleration, time);
+      body.update_coordinate(Vector::new(0.0, 0.0), time);
+    }
+  }
+}
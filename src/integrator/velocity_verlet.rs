@@ -0,0 +1,25 @@
+use super::{Field, Integrator};
+use crate::body::Body;
+use crate::float::Real;
+
+// x' = x + v * dt + a * dt^2 / 2, v' = v + (a + a') * dt / 2, with a' kept for the next step
+pub struct VelocityVerlet;
+
+impl Integrator for VelocityVerlet {
+  fn step(&self, bodies: &mut [Body], field: &mut dyn Field, time: Real) {
+    for body in bodies.iter_mut() {
+      body.update_coordinate(*body.get_acceleration(), time);
+    }
+
+    field.invalidate();
+    let next_accelerations = field.accelerations(bodies);
+    for (body, next_acceleration) in bodies.iter_mut().zip(next_accelerations) {
+      body.update_velocity(*body.get_acceleration() + next_acceleration, 0.5 * time);
+      body.set_acceleration(next_acceleration);
+    }
+  }
+
+  fn reuses_accelerations(&self) -> bool {
+    true
+  }
+}
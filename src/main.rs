@@ -1,5 +1,6 @@
 use amethyst::{
   prelude::*,
+  input::{InputBundle, StringBindings},
   renderer::{
       plugins::{RenderFlat2D, RenderToWindow},
       types::DefaultBackend,
@@ -11,9 +12,21 @@ use amethyst::core::transform::TransformBundle;
 
 mod quad_tree;
 mod body;
+mod boundary;
+mod camera;
+mod collision;
+mod diagnostics;
+mod float;
+mod integrator;
+mod oct_tree;
+mod parallel;
 mod vector;
 mod system;
+mod solver;
 mod systems;
+mod timestep;
+#[cfg(test)]
+mod test_support;
 
 use system::SystemState;
 
@@ -31,14 +44,16 @@ fn main() -> amethyst::Result<()> {
         )
         .with_plugin(RenderFlat2D::default()),
     )?
-    .with_bundle(TransformBundle::new())?
-    .with(systems::BodyQuadTreeSystem, "body_quad_tree_system", &[])
-    .with(systems::BodyForceSystem, "body_force_system", &["body_quad_tree_system"]);
+    .with_bundle(InputBundle::<StringBindings>::new())?
+    .with(systems::CameraOrbitSystem, "camera_orbit_system", &["input_system"])
+    .with(systems::CameraFollowSystem, "camera_follow_system", &["camera_orbit_system"])
+    .with(systems::BodyTransformSystem, "body_transform_system", &["camera_follow_system"])
+    .with_bundle(TransformBundle::new().with_dep(&["body_transform_system"]))?;
 
     let assets_dir = app_root.join("assets");
 
     let mut world = World::new();
-    let mut game = Application::new(assets_dir, SystemState, game_data)?;
+    let mut game = Application::new(assets_dir, SystemState::default(), game_data)?;
     game.run();
 
     Ok(())
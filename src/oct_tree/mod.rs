@@ -0,0 +1,233 @@
+use amethyst::ecs::prelude::{Component, DenseVecStorage};
+
+pub mod node;
+pub mod octant;
+
+use node::Node;
+use octant::Octant;
+use super::body::{Body, coordinate::Coordinate};
+use super::integrator::Field;
+use super::parallel::Walker;
+use super::vector::Vector;
+use crate::float::Real;
+use crate::quad_tree::{bounds::RootBounds, node::LeafLimits, ROOT_MARGIN};
+
+// Whether bodies move in the plane with the quad tree or in space with the oct tree, see `system::DIMENSIONS`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dimensions {
+  Two,
+  // The oct tree walk opens nodes with the classic Barnes-Hut angle `theta`
+  Three { theta: Real },
+}
+
+// Barnes-Hut tree over a cube, the 3D counterpart of `QuadTree`
+#[derive(Debug)]
+pub struct OctTree {
+  root: Box<Node>,
+  root_bounds: RootBounds,
+}
+
+impl OctTree {
+  pub fn new(length: Real) -> Self {
+    OctTree {
+      root: Box::new(Node::new(Octant::new(0.0, 0.0, 0.0, length))),
+      root_bounds: RootBounds::Fixed,
+    }
+  }
+
+  pub fn set_root_bounds(&mut self, root_bounds: RootBounds) {
+    self.root_bounds = root_bounds;
+  }
+
+  pub fn reset_root(&mut self) {
+    self.root.reset();
+  }
+
+  pub fn set_leaf_limits(&mut self, leaf_limits: LeafLimits) {
+    self.root.set_limits(leaf_limits);
+  }
+
+  // Bodies outside the root octant are left out, which only happens with `RootBounds::Fixed`
+  pub fn insert(&mut self, body: Body) {
+    if self.root.get_octant().contains(body.get_coordinate()) {
+      self.root.insert(body);
+    }
+  }
+
+  pub fn rebuild(&mut self, bodies: &[Body]) {
+    match self.get_fitted_octant(bodies) {
+      Some(octant) => self.root.set_octant(octant),
+      None => self.reset_root(),
+    }
+    for body in bodies {
+      self.insert(*body);
+    }
+  }
+
+  // Root octant around `bodies` as `root_bounds` asks, None to keep the current one. The cube
+  // counterpart of `QuadTree::get_fitted_quadrant`.
+  fn get_fitted_octant(&self, bodies: &[Body]) -> Option<Octant> {
+    if bodies.is_empty() {
+      return None;
+    }
+    let first = *bodies[0].get_coordinate();
+    let (min, max) = bodies.iter().skip(1).fold((first, first), |(min, max), body| {
+      let coordinate = body.get_coordinate();
+      (
+        Coordinate::new_3d(
+          min.get_x().min(coordinate.get_x()),
+          min.get_y().min(coordinate.get_y()),
+          min.get_z().min(coordinate.get_z()),
+        ),
+        Coordinate::new_3d(
+          max.get_x().max(coordinate.get_x()),
+          max.get_y().max(coordinate.get_y()),
+          max.get_z().max(coordinate.get_z()),
+        ),
+      )
+    });
+
+    match self.root_bounds {
+      RootBounds::Fixed => None,
+      RootBounds::BoundingBox => {
+        let size = (max.get_x() - min.get_x()).max(max.get_y() - min.get_y()).max(max.get_z() - min.get_z());
+        Some(Octant::new(
+          min.get_x() - ROOT_MARGIN,
+          min.get_y() - ROOT_MARGIN,
+          min.get_z() - ROOT_MARGIN,
+          size + 2.0 * ROOT_MARGIN,
+        ))
+      }
+      RootBounds::CentreOfMass => {
+        let mass: Real = bodies.iter().map(|body| body.get_mass()).sum();
+        let (x, y, z) = bodies.iter().fold((0.0, 0.0, 0.0), |(x, y, z), body| {
+          let coordinate = body.get_coordinate();
+          (
+            x + coordinate.get_x() * body.get_mass(),
+            y + coordinate.get_y() * body.get_mass(),
+            z + coordinate.get_z() * body.get_mass(),
+          )
+        });
+        let centre = Coordinate::new_3d(x / mass, y / mass, z / mass);
+        let half_length = (centre.get_x() - min.get_x())
+          .max(max.get_x() - centre.get_x())
+          .max(centre.get_y() - min.get_y())
+          .max(max.get_y() - centre.get_y())
+          .max(centre.get_z() - min.get_z())
+          .max(max.get_z() - centre.get_z())
+          + ROOT_MARGIN;
+
+        Some(Octant::new(
+          centre.get_x() - half_length,
+          centre.get_y() - half_length,
+          centre.get_z() - half_length,
+          2.0 * half_length,
+        ))
+      }
+    }
+  }
+
+  pub fn calculate_net_acceleration_on(&self, body: &Body, theta: Real) -> Vector {
+    self.root.calculate_net_acceleration_on(body, theta)
+  }
+
+  pub fn calculate_net_acceleration_and_jerk_on(&self, body: &Body, theta: Real) -> (Vector, Vector) {
+    self.root.calculate_net_acceleration_and_jerk_on(body, theta)
+  }
+}
+
+impl Component for OctTree {
+  type Storage = DenseVecStorage<Self>;
+}
+
+// Evaluates accelerations with the oct tree, following the same contract as `QuadTreeField`
+pub struct OctTreeField<'a> {
+  oct_tree: &'a mut OctTree,
+  theta: Real,
+  walker: &'a Walker,
+  is_current: bool,
+}
+
+impl<'a> OctTreeField<'a> {
+  pub fn new(oct_tree: &'a mut OctTree, theta: Real, walker: &'a Walker) -> Self {
+    OctTreeField {
+      oct_tree,
+      theta,
+      walker,
+      is_current: true,
+    }
+  }
+
+  fn prepare(&mut self, bodies: &[Body]) {
+    if !self.is_current {
+      self.oct_tree.rebuild(bodies);
+    }
+    self.is_current = false;
+  }
+}
+
+impl<'a> Field for OctTreeField<'a> {
+  fn accelerations(&mut self, bodies: &[Body]) -> Vec<Vector> {
+    self.prepare(bodies);
+    let (oct_tree, theta) = (&*self.oct_tree, self.theta);
+
+    self.walker.map(bodies, |body| oct_tree.calculate_net_acceleration_on(body, theta))
+  }
+
+  fn accelerations_and_jerks_of(&mut self, bodies: &[Body], active: &[usize]) -> Vec<(Vector, Vector)> {
+    self.prepare(bodies);
+    let (oct_tree, theta) = (&*self.oct_tree, self.theta);
+
+    self.walker.map(active, |&i| oct_tree.calculate_net_acceleration_and_jerk_on(&bodies[i], theta))
+  }
+
+  fn invalidate(&mut self) {
+    self.is_current = false;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Body, Coordinate, OctTree, Real, RootBounds, Vector};
+
+  #[test]
+  fn test_matches_pairwise_sum_when_fully_opened() {
+    let bodies: Vec<Body> = (0..30)
+      .map(|i| {
+        let i = i as Real;
+        Body::new(1.0 + i, Coordinate::new_3d(500.0 + 90.0 * (0.7 * i).cos(), 500.0 + 90.0 * (0.7 * i).sin(), 300.0 + 13.0 * i))
+      })
+      .collect();
+    let mut oct_tree = OctTree::new(1000.0);
+    oct_tree.rebuild(&bodies);
+
+    for body in &bodies {
+      let exact = bodies
+        .iter()
+        .filter(|other| other.get_id() != body.get_id())
+        .fold(Vector::new(0.0, 0.0), |acc, other| acc + other.calculate_acceleration_on(body));
+      let error = oct_tree.calculate_net_acceleration_on(body, 0.0) - exact;
+
+      assert!(error.get_x().abs() + error.get_y().abs() + error.get_z().abs() < 1e-6);
+    }
+  }
+
+  #[test]
+  fn test_rebuild_fits_root_around_escaped_bodies() {
+    let bodies = [
+      Body::new(10.0, Coordinate::new_3d(100.0, 100.0, 100.0)),
+      Body::new(10.0, Coordinate::new_3d(2500.0, 300.0, -3000.0)),
+    ];
+
+    for &root_bounds in &[RootBounds::BoundingBox, RootBounds::CentreOfMass] {
+      let mut oct_tree = OctTree::new(1000.0);
+      oct_tree.set_root_bounds(root_bounds);
+      oct_tree.rebuild(&bodies);
+
+      assert!(bodies.iter().all(|body| oct_tree.root.get_octant().contains(body.get_coordinate())));
+      let acceleration = oct_tree.calculate_net_acceleration_on(&bodies[1], 0.5);
+      assert!(acceleration.get_x() < 0.0 && acceleration.get_z() > 0.0);
+    }
+  }
+}
@@ -0,0 +1,210 @@
+use super::Body;
+use super::Octant;
+use super::Vector;
+use crate::float::Real;
+use crate::quad_tree::node::LeafLimits;
+
+#[derive(Debug)]
+pub struct Node {
+  body: Option<Body>,
+  // Eight children, ordered as `Octant::get_child_octants`
+  children: Option<Vec<Node>>,
+  // Bodies of a leaf, summed directly by the walks
+  bucket: Vec<Body>,
+  octant: Octant,
+  depth: usize,
+  limits: LeafLimits,
+}
+
+impl Node {
+  pub fn new(octant: Octant) -> Self {
+    Node::new_with_limits(octant, LeafLimits::default())
+  }
+
+  pub fn new_with_limits(octant: Octant, limits: LeafLimits) -> Self {
+    Node {
+      body: None,
+      children: None,
+      bucket: Vec::new(),
+      octant,
+      depth: 0,
+      limits,
+    }
+  }
+
+  pub fn reset(&mut self) {
+    self.body = None;
+    self.children = None;
+    self.bucket.clear();
+  }
+
+  pub fn set_limits(&mut self, limits: LeafLimits) {
+    self.reset();
+    self.limits = limits;
+  }
+
+  // Empty node over `octant`
+  pub fn set_octant(&mut self, octant: Octant) {
+    self.reset();
+    self.octant = octant;
+  }
+
+  pub fn get_octant(&self) -> &Octant {
+    &self.octant
+  }
+
+  pub fn insert(&mut self, body: Body) {
+    self.body = Some(match self.body {
+      Some(existing_body) => existing_body.add_body(&body),
+      None => body,
+    });
+
+    if self.children.is_some() {
+      self.insert_into_child(body);
+      return;
+    }
+
+    self.bucket.push(body);
+    if self.bucket.len() > self.limits.capacity && self.depth < self.limits.max_depth {
+      let (limits, depth) = (self.limits, self.depth + 1);
+      self.children = Some(
+        self
+          .octant
+          .get_child_octants()
+          .into_iter()
+          .map(|octant| {
+            let mut child = Node::new_with_limits(octant, limits);
+            child.depth = depth;
+            child
+          })
+          .collect(),
+      );
+      for body in std::mem::take(&mut self.bucket) {
+        self.insert_into_child(body);
+      }
+    }
+  }
+
+  fn insert_into_child(&mut self, body: Body) {
+    let children = self.children.as_mut().unwrap();
+    match children.iter_mut().find(|child| child.octant.contains(body.get_coordinate())) {
+      Some(child) => child.insert(body),
+      None => panic!("body doesn't belong to any octant"),
+    }
+  }
+
+  // Bodies in the bucket other than `body`, summed directly at the leaves
+  fn get_other_bodies<'a>(&'a self, body: &'a Body) -> impl Iterator<Item = &'a Body> {
+    self.bucket.iter().filter(move |other| other.get_id() != body.get_id())
+  }
+
+  pub fn calculate_net_acceleration_on(&self, body: &Body, theta: Real) -> Vector {
+    match self.body {
+      Some(self_body) => {
+        if self_body.get_id() == body.get_id() {
+          return Vector::new(0.0, 0.0);
+        }
+
+        let distance = self_body.get_distance_between(body);
+        if self.octant.get_length() / distance < theta {
+          return self_body.calculate_acceleration_on(body);
+        }
+
+        match &self.children {
+          Some(children) => children
+            .iter()
+            .fold(Vector::new(0.0, 0.0), |acc, child| acc + child.calculate_net_acceleration_on(body, theta)),
+          None => self
+            .get_other_bodies(body)
+            .fold(Vector::new(0.0, 0.0), |acc, other| acc + other.calculate_acceleration_on(body)),
+        }
+      }
+      None => Vector::new(0.0, 0.0),
+    }
+  }
+
+  // Same walk as `calculate_net_acceleration_on`, returning the acceleration and jerk on `body`
+  pub fn calculate_net_acceleration_and_jerk_on(&self, body: &Body, theta: Real) -> (Vector, Vector) {
+    match self.body {
+      Some(self_body) => {
+        if self_body.get_id() == body.get_id() {
+          return (Vector::new(0.0, 0.0), Vector::new(0.0, 0.0));
+        }
+
+        let distance = self_body.get_distance_between(body);
+        if self.octant.get_length() / distance < theta {
+          return self_body.calculate_acceleration_and_jerk_on(body);
+        }
+
+        let sum = |(acceleration, jerk): (Vector, Vector), (other_acceleration, other_jerk): (Vector, Vector)| {
+          (acceleration + other_acceleration, jerk + other_jerk)
+        };
+        match &self.children {
+          Some(children) => children.iter().fold((Vector::new(0.0, 0.0), Vector::new(0.0, 0.0)), |acc, child| {
+            sum(acc, child.calculate_net_acceleration_and_jerk_on(body, theta))
+          }),
+          None => self.get_other_bodies(body).fold((Vector::new(0.0, 0.0), Vector::new(0.0, 0.0)), |acc, other| {
+            sum(acc, other.calculate_acceleration_and_jerk_on(body))
+          }),
+        }
+      }
+      None => (Vector::new(0.0, 0.0), Vector::new(0.0, 0.0)),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Body, LeafLimits, Node, Octant};
+  use crate::body::coordinate::Coordinate;
+
+  #[test]
+  fn insert_on_node_multiple_level() {
+    let mut node = Node::new(Octant::new(0.0, 0.0, 0.0, 1000.0));
+    node.insert(Body::new(10.0, Coordinate::new_3d(100.0, 100.0, 100.0)));
+    node.insert(Body::new(30.0, Coordinate::new_3d(200.0, 100.0, 300.0)));
+
+    let body = node.body.unwrap();
+    assert_eq!(body.get_mass(), 40.0);
+    assert_eq!(body.get_coordinate().get_x(), 175.0);
+    assert_eq!(body.get_coordinate().get_z(), 250.0);
+
+    // Both land in the lowest octant, then split along z
+    let children = node.children.unwrap();
+    assert_eq!(children[0].body.unwrap().get_mass(), 40.0);
+    let grandchildren = children[0].children.as_ref().unwrap();
+    assert_eq!(grandchildren[0].body.unwrap().get_mass(), 10.0);
+    assert_eq!(grandchildren[4].body.unwrap().get_mass(), 30.0);
+  }
+
+  #[test]
+  fn coincident_bodies_share_a_leaf_at_max_depth() {
+    let mut node = Node::new_with_limits(Octant::new(0.0, 0.0, 0.0, 1000.0), LeafLimits { capacity: 1, max_depth: 6 });
+    (0..3).for_each(|_| node.insert(Body::new(10.0, Coordinate::new_3d(300.0, 300.0, 300.0))));
+
+    let mut leaf = &node;
+    while let Some(children) = &leaf.children {
+      leaf = children.iter().find(|child| child.body.is_some()).unwrap();
+    }
+
+    assert_eq!(leaf.depth, 6);
+    assert_eq!(leaf.bucket.len(), 3);
+    assert_eq!(node.body.unwrap().get_mass(), 30.0);
+  }
+
+  #[test]
+  fn bucket_is_summed_directly() {
+    let mut node = Node::new_with_limits(Octant::new(0.0, 0.0, 0.0, 1000.0), LeafLimits { capacity: 4, max_depth: 32 });
+    let bodies = [
+      Body::new(10.0, Coordinate::new_3d(100.0, 100.0, 100.0)),
+      Body::new(40.0, Coordinate::new_3d(180.0, 130.0, 120.0)),
+      Body::new(25.0, Coordinate::new_3d(120.0, 210.0, 90.0)),
+    ];
+    bodies.iter().for_each(|body| node.insert(*body));
+    let target = bodies[0];
+
+    let exact = bodies[1].calculate_acceleration_on(&target) + bodies[2].calculate_acceleration_on(&target);
+    assert!(node.children.is_none());
+    assert_eq!(node.calculate_net_acceleration_on(&target, 0.5), exact);
+  }
+}
@@ -0,0 +1,62 @@
+use super::Coordinate;
+use crate::float::Real;
+
+// Axis-aligned cube with its lowest corner at (x, y, z)
+#[derive(Debug, Copy, Clone)]
+pub struct Octant {
+  x: Real,
+  y: Real,
+  z: Real,
+  length: Real,
+}
+
+impl Octant {
+  pub fn new(x: Real, y: Real, z: Real, length: Real) -> Self {
+    Octant {
+      x,
+      y,
+      z,
+      length,
+    }
+  }
+
+  pub fn get_length(&self) -> Real {
+    self.length
+  }
+
+  // Child `i` takes the upper half along x when bit 0 of `i` is set, along y for bit 1 and along z for bit 2
+  pub fn get_child_octants(&self) -> Vec<Octant> {
+    let child_length = self.length / 2.0;
+    (0..8)
+      .map(|i| Octant {
+        x: self.x + if i & 1 != 0 { child_length } else { 0.0 },
+        y: self.y + if i & 2 != 0 { child_length } else { 0.0 },
+        z: self.z + if i & 4 != 0 { child_length } else { 0.0 },
+        length: child_length,
+      })
+      .collect()
+  }
+
+  pub fn contains(&self, coordinate: &Coordinate) -> bool {
+    let within = |value: Real, start: Real| value >= start && value <= start + self.length;
+
+    within(coordinate.get_x(), self.x) && within(coordinate.get_y(), self.y) && within(coordinate.get_z(), self.z)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Coordinate, Octant};
+
+  #[test]
+  fn test_get_child_octants() {
+    let octant = Octant::new(0.0, 0.0, 0.0, 500.0);
+    let children = octant.get_child_octants();
+
+    assert_eq!(children.len(), 8);
+    assert!(children[0].contains(&Coordinate::new_3d(100.0, 100.0, 100.0)));
+    assert!(children[5].contains(&Coordinate::new_3d(300.0, 100.0, 300.0)));
+    assert!(!children[5].contains(&Coordinate::new_3d(300.0, 300.0, 300.0)));
+    assert!(children.iter().all(|child| child.get_length() == 250.0));
+  }
+}
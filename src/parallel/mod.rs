@@ -0,0 +1,66 @@
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+
+// Runs the per-body tree walks, on a pool of its own or on the calling thread when it has one thread.
+// Every body's walk is the same sequence of operations either way and the results keep the input
+// order, so the forces are bit-identical to the serial ones.
+pub struct Walker {
+  pool: Option<ThreadPool>,
+}
+
+impl Walker {
+  // 0 threads means one per core
+  pub fn new(threads: usize) -> Self {
+    if threads == 1 {
+      return Walker::serial();
+    }
+
+    let pool = ThreadPoolBuilder::new()
+      .num_threads(threads)
+      .thread_name(|i| format!("force-walk-{}", i))
+      .build()
+      .expect("failed to build the force walk thread pool");
+    Walker { pool: Some(pool) }
+  }
+
+  pub fn serial() -> Self {
+    Walker { pool: None }
+  }
+
+  pub fn map<I, T, F>(&self, items: &[I], walk: F) -> Vec<T>
+  where
+    I: Sync,
+    T: Send,
+    F: Fn(&I) -> T + Sync + Send,
+  {
+    match &self.pool {
+      Some(pool) => pool.install(|| items.par_iter().map(walk).collect()),
+      None => items.iter().map(walk).collect(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Walker;
+  use crate::test_support::get_spiral;
+  use crate::integrator::Field;
+  use crate::quad_tree::{QuadTree, opening_criterion::OpeningCriterion};
+  use crate::solver::QuadTreeField;
+
+  #[test]
+  fn test_parallel_walk_matches_serial() {
+    let bodies = get_spiral(200);
+    let mut quad_tree = QuadTree::new(1000.0);
+    quad_tree.rebuild(&bodies);
+    let criterion = OpeningCriterion::BarnesHut { theta: 0.7 };
+    let (serial, parallel) = (Walker::serial(), Walker::new(4));
+
+    let expected = QuadTreeField::new(&mut quad_tree, criterion, &serial).accelerations(&bodies);
+    let actual = QuadTreeField::new(&mut quad_tree, criterion, &parallel).accelerations(&bodies);
+    assert_eq!(actual, expected);
+
+    let expected = QuadTreeField::new(&mut quad_tree, criterion, &serial).accelerations_and_jerks(&bodies);
+    let actual = QuadTreeField::new(&mut quad_tree, criterion, &parallel).accelerations_and_jerks(&bodies);
+    assert_eq!(actual, expected);
+  }
+}
@@ -0,0 +1,78 @@
+use super::Coordinate;
+use crate::float::Real;
+
+// How the root quadrant is chosen on every rebuild, see `system::ROOT_BOUNDS`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RootBounds {
+  // The quadrant the tree was created with, bodies outside it are left out
+  Fixed,
+  // The smallest square holding every body
+  BoundingBox,
+  // The smallest square centred on the centre of mass holding every body
+  CentreOfMass,
+}
+
+// Axis-aligned box around the bodies actually held by a node, which can be much smaller than its quadrant
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bounds {
+  min: Coordinate,
+  max: Coordinate,
+}
+
+impl Bounds {
+  pub fn new(coordinate: Coordinate) -> Self {
+    Bounds {
+      min: coordinate,
+      max: coordinate,
+    }
+  }
+
+  pub fn get_min(&self) -> &Coordinate {
+    &self.min
+  }
+
+  pub fn get_max(&self) -> &Coordinate {
+    &self.max
+  }
+
+  pub fn add_coordinate(&self, coordinate: &Coordinate) -> Self {
+    self.add_bounds(&Bounds::new(*coordinate))
+  }
+
+  pub fn add_bounds(&self, other: &Bounds) -> Self {
+    Bounds {
+      min: Coordinate::new(self.min.get_x().min(other.min.get_x()), self.min.get_y().min(other.min.get_y())),
+      max: Coordinate::new(self.max.get_x().max(other.max.get_x()), self.max.get_y().max(other.max.get_y())),
+    }
+  }
+
+  // Longest side of the box
+  pub fn get_size(&self) -> Real {
+    (self.max.get_x() - self.min.get_x()).max(self.max.get_y() - self.min.get_y())
+  }
+
+  // Distance from `coordinate` to the farthest corner, an upper bound on the distance to any body inside
+  pub fn get_max_distance_from(&self, coordinate: &Coordinate) -> Real {
+    let dx = (coordinate.get_x() - self.min.get_x()).abs().max((self.max.get_x() - coordinate.get_x()).abs());
+    let dy = (coordinate.get_y() - self.min.get_y()).abs().max((self.max.get_y() - coordinate.get_y()).abs());
+
+    (dx.powi(2) + dy.powi(2)).sqrt()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Bounds, Coordinate};
+
+  #[test]
+  fn test_add_coordinate() {
+    let bounds = Bounds::new(Coordinate::new(10.0, 20.0))
+      .add_coordinate(&Coordinate::new(30.0, 5.0))
+      .add_coordinate(&Coordinate::new(15.0, 12.0));
+
+    assert_eq!(bounds, Bounds { min: Coordinate::new(10.0, 5.0), max: Coordinate::new(30.0, 20.0) });
+    assert_eq!(bounds.get_size(), 20.0);
+    assert_eq!(bounds.get_max_distance_from(&Coordinate::new(10.0, 5.0)), 25.0);
+  }
+}
@@ -1,24 +1,69 @@
+use std::mem;
 use amethyst::ecs::prelude::{Component, DenseVecStorage};
+use rayon::prelude::*;
 
+pub mod bounds;
+pub mod morton;
 pub mod node;
+pub mod opening_criterion;
+pub mod periodic;
 pub mod quadrant;
+pub mod quadrupole;
 
-use std::mem;
-use node::Node;
+use bounds::{Bounds, RootBounds};
+use node::{LeafLimits, Node, NodeArena};
+use opening_criterion::OpeningCriterion;
+use periodic::PeriodicDomain;
 use quadrant::Quadrant;
+use quadrupole::Expansion;
 use super::body::{Body, coordinate::Coordinate};
 use super::vector::Vector;
+use crate::float::Real;
+
+// How `QuadTree::rebuild` fills the tree, see `system::TREE_BUILD`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TreeBuild {
+  // Every body inserted from the root, one at a time
+  Serial,
+  // From `min_bodies` bodies, the 4^levels subdomains `levels` below the root are built in parallel
+  // from their share of the bodies and merged
+  Parallel { levels: usize, min_bodies: usize },
+  // Bodies sorted along the Z curve and the tree cut from contiguous ranges of them, see `morton`
+  Morton,
+}
 
 #[derive(Debug)]
 pub struct QuadTree {
   root: Box<Node>,
+  expansion: Expansion,
+  root_bounds: RootBounds,
+  leaf_limits: LeafLimits,
+  build: TreeBuild,
+  // Full rebuilds happen every this many steps, with refits in between. None rebuilds on every one.
+  rebuild_interval: Option<usize>,
+  // Set when the root quadrant is periodic, which keeps it fixed
+  domain: Option<PeriodicDomain>,
+  // Trees the parallel build fills and merges, kept for the next one
+  subtrees: Vec<QuadTree>,
 }
 
+// Keeps bodies on the edge of a fitted root inside it, and coincident bodies in a finite quadrant. The
+// oct tree fits its root octant with the same margin.
+pub const ROOT_MARGIN: Real = 1.0;
+
 impl QuadTree {
-  pub fn new(length: f32) -> Self {
+  pub fn new(length: Real) -> Self {
     let quadrant = Quadrant::new(0.0, 0.0, length);
     QuadTree {
-      root: Box::new(Node::new(quadrant))
+      root: Box::new(Node::new(quadrant)),
+      expansion: Expansion::Monopole,
+      root_bounds: RootBounds::Fixed,
+      leaf_limits: LeafLimits::default(),
+      build: TreeBuild::Serial,
+      rebuild_interval: None,
+      domain: None,
+      subtrees: Vec::new(),
     }
   }
 
@@ -28,16 +73,74 @@ impl QuadTree {
 
   pub fn new_with_quadrant(quadrant: Quadrant) -> Self {
     QuadTree {
-      root: Box::new(Node::new(quadrant))
+      root: Box::new(Node::new(quadrant)),
+      expansion: Expansion::Monopole,
+      root_bounds: RootBounds::Fixed,
+      leaf_limits: LeafLimits::default(),
+      build: TreeBuild::Serial,
+      rebuild_interval: None,
+      domain: None,
+      subtrees: Vec::new(),
+    }
+  }
+
+  // Empty tree over the same quadrant, with the same settings
+  pub fn new_like(&self) -> Self {
+    QuadTree {
+      root: Box::new(Node::new_with_limits(*self.root.get_quadrant(), self.leaf_limits)),
+      expansion: self.expansion,
+      root_bounds: self.root_bounds,
+      leaf_limits: self.leaf_limits,
+      build: self.build,
+      rebuild_interval: self.rebuild_interval,
+      domain: self.domain.clone(),
+      subtrees: Vec::new(),
     }
   }
 
+  pub fn set_expansion(&mut self, expansion: Expansion) {
+    self.expansion = expansion;
+  }
+
+  pub fn set_root_bounds(&mut self, root_bounds: RootBounds) {
+    self.root_bounds = root_bounds;
+  }
+
+  pub fn set_leaf_limits(&mut self, leaf_limits: LeafLimits) {
+    self.leaf_limits = leaf_limits;
+    self.root.reuse(*self.root.get_quadrant(), leaf_limits, 0);
+  }
+
+  pub fn set_build(&mut self, build: TreeBuild) {
+    self.build = build;
+  }
+
+  pub fn set_rebuild_interval(&mut self, rebuild_interval: Option<usize>) {
+    self.rebuild_interval = rebuild_interval;
+  }
+
+  pub fn get_domain(&self) -> Option<&PeriodicDomain> {
+    self.domain.as_ref()
+  }
+
+  pub fn set_periodic(&mut self, periodic: bool) {
+    self.domain = match periodic {
+      true => Some(PeriodicDomain::new(*self.get_root().get_quadrant())),
+      false => None,
+    };
+  }
+
+  #[allow(clippy::borrowed_box)]
   pub fn get_root(&self) -> &Box<Node> {
       &self.root
   }
 
+  pub fn get_nodes(&self) -> &NodeArena {
+    self.root.get_nodes()
+  }
+
   pub fn insert(&mut self, body: Body) {
-    if self.root.get_quadrant().contains(&body.get_coordinate()) {
+    if let Some(body) = self.get_insertable(body) {
       self.root.insert(body);
     }
     // else {
@@ -45,19 +148,155 @@ impl QuadTree {
     // }
   }
 
+  // `body` as the root takes it, None when it is outside the root
+  fn get_insertable(&self, mut body: Body) -> Option<Body> {
+    // Nothing is outside a periodic domain
+    if let Some(domain) = &self.domain {
+      domain.wrap(&mut body);
+    }
+    if self.get_root().get_quadrant().contains(&body.get_coordinate()) {
+      Some(body)
+    } else {
+      None
+    }
+  }
+
+  pub fn rebuild(&mut self, bodies: &[Body]) {
+    match self.get_fitted_quadrant(bodies) {
+      Some(quadrant) => self.root.reuse(quadrant, self.leaf_limits, 0),
+      None => self.reset_root(),
+    }
+
+    match self.build {
+      TreeBuild::Parallel { levels, min_bodies } if bodies.len() >= min_bodies => {
+        let bodies = bodies.iter().filter_map(|body| self.get_insertable(*body)).collect();
+        self.build_parallel(bodies, levels);
+      }
+      TreeBuild::Morton => {
+        let bodies: Vec<Body> = bodies.iter().filter_map(|body| self.get_insertable(*body)).collect();
+        self.root.build_morton(&bodies);
+      }
+      _ => {
+        for body in bodies {
+          self.insert(*body);
+        }
+      }
+    }
+  }
+
+  // Rebuilds the tree for `bodies` at the start of physics step `step`, or refits it when the rebuild
+  // interval asks for that
+  pub fn update(&mut self, bodies: &[Body], step: usize) {
+    match self.rebuild_interval {
+      Some(interval) if step % interval.max(1) != 0 => self.refit(bodies),
+      _ => self.rebuild(bodies),
+    }
+  }
+
+  // Keeps the nodes of the last build, moving the bodies that crossed into another leaf and
+  // recomputing the aggregates. The root stays where it is, so a body outside it means a rebuild.
+  pub fn refit(&mut self, bodies: &[Body]) {
+    let insertable: Option<Vec<Body>> = bodies.iter().map(|body| self.get_insertable(*body)).collect();
+    match insertable {
+      Some(insertable) => self.root.refit(&insertable),
+      None => self.rebuild(bodies),
+    }
+  }
+
+  // Fills the empty tree with `bodies`. The 4^levels subdomains `levels` below the root are built in
+  // parallel as trees of their own and merged back up; the tree has the same shape as one built by
+  // inserting.
+  fn build_parallel(&mut self, bodies: Vec<Body>, levels: usize) {
+    let (quadrant, limits, depth) = (*self.root.get_quadrant(), self.root.get_limits(), self.root.get_depth());
+    // Inserting doesn't split these either
+    if levels == 0 || bodies.len() <= limits.capacity || depth >= limits.max_depth {
+      bodies.into_iter().for_each(|body| self.root.insert(body));
+      return;
+    }
+
+    let (ne, nw, se, sw) = quadrant.get_child_quadrants();
+    let mut parts = [(ne, Vec::new()), (nw, Vec::new()), (se, Vec::new()), (sw, Vec::new())];
+    for body in bodies {
+      // Bodies on a shared edge go where `NodeArena::get_containing_child_node` would put them
+      let part = [1, 0, 3, 2]
+        .iter()
+        .copied()
+        .find(|&i| parts[i].0.contains(body.get_coordinate()))
+        .expect("body doesn't belong to any quadrant");
+      parts[part].1.push(body);
+    }
+
+    let mut trees = mem::take(&mut self.subtrees);
+    trees.resize_with(parts.len(), || QuadTree::new_with_quadrant(quadrant));
+    trees.par_iter_mut().zip(parts[..].par_iter_mut()).for_each(|(tree, (quadrant, bodies))| {
+      tree.root.reuse(*quadrant, limits, depth + 1);
+      tree.build_parallel(mem::take(bodies), levels - 1);
+    });
+    self.merge(&mut trees);
+  }
+
+  // Makes the roots of `trees` the children of the root, in (ne, nw, se, sw) order. They are copied in,
+  // and the trees are taken out of the vector and kept for the next parallel build.
   pub fn merge(&mut self, trees: &mut Vec<Self>) {
-    let mut nodes = trees
-      .iter_mut()
-      .map(|tree| {
-        let root = mem::replace(&mut tree.root, Box::new(Node::new(Quadrant::new(0.0, 0.0, 0.0))));
-        root
-      })
-      .collect();
-    self.root.merge(&mut nodes);
+    match trees.as_slice() {
+      [ne, nw, se, sw] => self.root.merge_nodes([&ne.root, &nw.root, &se.root, &sw.root]),
+      _ => panic!("a tree merges four subtrees"),
+    }
+    self.subtrees.append(trees);
+  }
+
+  // Root quadrant around `bodies` as `root_bounds` asks, None to keep the current one
+  fn get_fitted_quadrant(&self, bodies: &[Body]) -> Option<Quadrant> {
+    if self.domain.is_some() || bodies.is_empty() {
+      return None;
+    }
+    let bounds = bodies
+      .iter()
+      .skip(1)
+      .fold(Bounds::new(*bodies[0].get_coordinate()), |bounds, body| bounds.add_coordinate(body.get_coordinate()));
+    let (min, max) = (bounds.get_min(), bounds.get_max());
+
+    match self.root_bounds {
+      RootBounds::Fixed => None,
+      RootBounds::BoundingBox => Some(Quadrant::new(
+        min.get_x() - ROOT_MARGIN,
+        min.get_y() - ROOT_MARGIN,
+        bounds.get_size() + 2.0 * ROOT_MARGIN,
+      )),
+      RootBounds::CentreOfMass => {
+        let mass: Real = bodies.iter().map(|body| body.get_mass()).sum();
+        let (x, y) = bodies.iter().fold((0.0, 0.0), |(x, y), body| {
+          (x + body.get_coordinate().get_x() * body.get_mass(), y + body.get_coordinate().get_y() * body.get_mass())
+        });
+        let centre = Coordinate::new(x / mass, y / mass);
+        let half_length = (centre.get_x() - min.get_x())
+          .max(max.get_x() - centre.get_x())
+          .max(centre.get_y() - min.get_y())
+          .max(max.get_y() - centre.get_y())
+          + ROOT_MARGIN;
+
+        Some(Quadrant::new(centre.get_x() - half_length, centre.get_y() - half_length, 2.0 * half_length))
+      }
+    }
   }
 
-  pub fn calculate_net_force_on(&self, body: &Body, theta: f32) -> Vector {
-    self.root.calculate_net_force_on(body, theta)
+  // Bodies in the tree no further than `distance` from `coordinate`
+  pub fn find_bodies_within(&self, coordinate: &Coordinate, distance: Real) -> Vec<Body> {
+    let mut found = Vec::new();
+    self.root.find_bodies_within(coordinate, distance, &mut found);
+    found
+  }
+
+  pub fn calculate_net_force_on(&self, body: &Body, criterion: OpeningCriterion) -> Vector {
+    self.root.calculate_net_force_on(body, criterion, self.expansion, self.domain.as_ref())
+  }
+
+  pub fn calculate_potential_energy_of(&self, body: &Body, criterion: OpeningCriterion) -> Real {
+    self.root.calculate_potential_energy_of(body, criterion, self.expansion, self.domain.as_ref())
+  }
+
+  pub fn calculate_net_acceleration_and_jerk_on(&self, body: &Body, criterion: OpeningCriterion) -> (Vector, Vector) {
+    self.root.calculate_net_acceleration_and_jerk_on(body, criterion, self.expansion, self.domain.as_ref())
   }
 }
 
@@ -67,7 +306,11 @@ impl Component for QuadTree {
 
 #[cfg(test)]
 mod tests {
-  use super::{Body, Coordinate, QuadTree};
+  use super::{Body, Coordinate, Expansion, LeafLimits, NodeArena, QuadTree, RootBounds, TreeBuild, Vector};
+  use super::node::ArenaNode;
+  use super::opening_criterion::OpeningCriterion;
+  use crate::test_support::get_spiral;
+  use crate::solver::compare_forces;
 
   #[test]
   fn test_insert() {
@@ -76,7 +319,157 @@ mod tests {
 
     quad_tree.insert(body);
 
-    assert!(quad_tree.root.get_body().is_some());
+    assert!(quad_tree.get_root().get_body().is_some());
+  }
+
+  #[test]
+  fn test_find_bodies_within() {
+    let mut quad_tree = QuadTree::new(1000.0);
+    quad_tree.insert(Body::new(10.0, Coordinate::new(100.0, 100.0)));
+    quad_tree.insert(Body::new(10.0, Coordinate::new(110.0, 100.0)));
+    quad_tree.insert(Body::new(10.0, Coordinate::new(900.0, 900.0)));
+
+    let found = quad_tree.find_bodies_within(&Coordinate::new(105.0, 100.0), 20.0);
+
+    assert_eq!(found.len(), 2);
+    assert!(found.iter().all(|body| body.get_coordinate().get_x() < 200.0));
+  }
+
+  #[test]
+  fn test_rebuild_fits_root_around_escaped_bodies() {
+    let bodies = [
+      Body::new(10.0, Coordinate::new(100.0, 100.0)),
+      Body::new(10.0, Coordinate::new(2500.0, 3000.0)),
+    ];
+    let criterion = OpeningCriterion::BarnesHut { theta: 0.5 };
+
+    for &root_bounds in &[RootBounds::BoundingBox, RootBounds::CentreOfMass] {
+      let mut quad_tree = QuadTree::new(1000.0);
+      quad_tree.set_root_bounds(root_bounds);
+      quad_tree.rebuild(&bodies);
+
+      assert!(bodies.iter().all(|body| quad_tree.get_root().get_quadrant().contains(body.get_coordinate())));
+      let force = quad_tree.calculate_net_force_on(&bodies[1], criterion);
+      assert!(force.get_x() < 0.0 && force.get_y() < 0.0);
+    }
+  }
+
+  fn build_with(bodies: &[Body], build: TreeBuild) -> QuadTree {
+    let mut quad_tree = QuadTree::new(1000.0);
+    quad_tree.set_leaf_limits(LeafLimits { capacity: 4, max_depth: 32 });
+    quad_tree.set_build(build);
+    quad_tree.rebuild(bodies);
+    quad_tree
+  }
+
+  // Nodes of the tree depth first, children in (ne, nw, se, sw) order
+  fn get_nodes<'a>(nodes: &'a NodeArena, node: &'a ArenaNode, found: &mut Vec<&'a ArenaNode>) {
+    found.push(node);
+    if let Some(children) = node.get_children() {
+      [children.ne, children.nw, children.se, children.sw]
+        .iter()
+        .for_each(|&child| get_nodes(nodes, nodes.get(child), found));
+    }
+  }
+
+  // Bucket sizes of the leaves, depth first
+  fn get_leaves(quad_tree: &QuadTree) -> Vec<usize> {
+    let mut found = Vec::new();
+    get_nodes(quad_tree.get_nodes(), quad_tree.get_nodes().get_root(), &mut found);
+    found.iter().filter(|node| node.get_children().is_none()).map(|node| node.get_bucket().len()).collect()
+  }
+
+  #[test]
+  fn test_parallel_build_matches_serial() {
+    let bodies = get_spiral(500);
+    let serial = build_with(&bodies, TreeBuild::Serial);
+    let parallel = build_with(&bodies, TreeBuild::Parallel { levels: 2, min_bodies: 0 });
+
+    assert_eq!(get_leaves(&parallel), get_leaves(&serial));
+
+    let criterion = OpeningCriterion::BarnesHut { theta: 0.5 };
+    for body in &bodies {
+      let expected = serial.calculate_net_force_on(body, criterion);
+      let error = (parallel.calculate_net_force_on(body, criterion) - expected).get_norm() / expected.get_norm();
+      assert!(error < 1e-4);
+    }
+  }
+
+  #[test]
+  fn test_jerk_walk_uses_the_expansion() {
+    let bodies = get_spiral(200);
+    let mut quad_tree = build_with(&bodies, TreeBuild::Serial);
+    quad_tree.set_expansion(Expansion::Quadrupole);
+    let criterion = OpeningCriterion::BarnesHut { theta: 0.7 };
+
+    for body in &bodies {
+      let expected = quad_tree.calculate_net_force_on(body, criterion) / body.get_mass();
+      let (acceleration, _) = quad_tree.calculate_net_acceleration_and_jerk_on(body, criterion);
+      assert!((acceleration - expected).get_norm() / expected.get_norm() < 1e-4);
+    }
+  }
+
+  #[test]
+  fn test_morton_build_matches_insert() {
+    let bodies = get_spiral(500);
+    let serial = build_with(&bodies, TreeBuild::Serial);
+    let morton = build_with(&bodies, TreeBuild::Morton);
+
+    assert_eq!(get_leaves(&morton), get_leaves(&serial));
+
+    let (mut expected, mut actual) = (Vec::new(), Vec::new());
+    get_nodes(serial.get_nodes(), serial.get_nodes().get_root(), &mut expected);
+    get_nodes(morton.get_nodes(), morton.get_nodes().get_root(), &mut actual);
+    for (expected, actual) in expected.iter().zip(&actual) {
+      match (expected.get_body(), actual.get_body()) {
+        (Some(expected), Some(actual)) => {
+          assert!((actual.get_mass() - expected.get_mass()).abs() < 1e-4);
+          assert!(actual.get_coordinate().get_distance_between(*expected.get_coordinate()) < 1e-3);
+        }
+        (expected, actual) => assert_eq!(expected.is_some(), actual.is_some()),
+      }
+    }
+  }
+
+  #[test]
+  fn test_refit_moves_bodies_between_leaves() {
+    let mut bodies = get_spiral(300);
+    let mut quad_tree = build_with(&bodies, TreeBuild::Serial);
+    let leaves = get_leaves(&quad_tree).len();
+    bodies.iter_mut().for_each(|body| body.set_coordinate(*body.get_coordinate() + Vector::new(7.0, -4.0)));
+
+    quad_tree.refit(&bodies);
+
+    assert_eq!(get_leaves(&quad_tree).len(), leaves);
+    assert_eq!(get_leaves(&quad_tree).iter().sum::<usize>(), bodies.len());
+    let rebuilt = build_with(&bodies, TreeBuild::Serial);
+    let (root, rebuilt_root) = (quad_tree.get_root().get_body().unwrap(), rebuilt.get_root().get_body().unwrap());
+    assert!(root.get_coordinate().get_distance_between(*rebuilt_root.get_coordinate()) < 1e-3);
+    let errors = compare_forces(&quad_tree, &bodies, OpeningCriterion::BarnesHut { theta: 0.0 });
+    assert!(errors.iter().all(|&error| error < 1e-4));
+  }
+
+  #[test]
+  fn test_update_rebuilds_every_interval() {
+    let bodies = get_spiral(100);
+    let mut quad_tree = build_with(&[], TreeBuild::Serial);
+    quad_tree.set_rebuild_interval(Some(3));
+    quad_tree.update(&bodies, 0);
+    let leaves = get_leaves(&quad_tree).len();
+
+    // Bunched into one corner, which only a rebuild splits further
+    let bunched: Vec<Body> = bodies
+      .iter()
+      .map(|body| {
+        let coordinate = body.get_coordinate();
+        Body::new(body.get_mass(), Coordinate::new(300.0 + coordinate.get_x() / 20.0, 300.0 + coordinate.get_y() / 20.0))
+      })
+      .collect();
+    quad_tree.update(&bunched, 1);
+    quad_tree.update(&bunched, 2);
+    assert_eq!(get_leaves(&quad_tree).len(), leaves);
+    quad_tree.update(&bunched, 3);
+    assert!(get_leaves(&quad_tree).len() > leaves);
   }
 
   #[test]
@@ -0,0 +1,55 @@
+use super::Coordinate;
+use super::Quadrant;
+use crate::float::to_f64;
+
+// Levels a key resolves, two bits each
+pub const LEVELS: usize = 32;
+
+// Position of `coordinate` along the Z curve through `quadrant`. The two bits of each level, from the
+// top, are (y, x), so a node's children come in (sw, se, nw, ne) order and the bodies of any node are
+// a contiguous range of keys. Coordinates on the edge between two cells go west and north, as they do
+// when inserting.
+pub fn get_key(quadrant: &Quadrant, coordinate: &Coordinate) -> u64 {
+  let scale = |value, start| (to_f64(value) - to_f64(start)) / to_f64(quadrant.get_length()) * (1u64 << LEVELS) as f64;
+  let clamp = |cells: f64| cells.max(0.0).min(((1u64 << LEVELS) - 1) as f64) as u64;
+  let x = clamp(scale(coordinate.get_x(), quadrant.get_x()).ceil() - 1.0);
+  let y = clamp(scale(coordinate.get_y(), quadrant.get_y()).floor());
+
+  spread(y) << 1 | spread(x)
+}
+
+// Child of the node at `depth` the key falls in, 0 to 3 in (sw, se, nw, ne) order
+pub fn get_digit(key: u64, depth: usize) -> usize {
+  (key >> (2 * (LEVELS - 1 - depth)) & 3) as usize
+}
+
+// Moves the low 32 bits of `value` to the even bits
+fn spread(value: u64) -> u64 {
+  let mut value = value & 0xffff_ffff;
+  value = (value | value << 16) & 0x0000_ffff_0000_ffff;
+  value = (value | value << 8) & 0x00ff_00ff_00ff_00ff;
+  value = (value | value << 4) & 0x0f0f_0f0f_0f0f_0f0f;
+  value = (value | value << 2) & 0x3333_3333_3333_3333;
+  (value | value << 1) & 0x5555_5555_5555_5555
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{get_digit, get_key, Coordinate, Quadrant};
+
+  #[test]
+  fn test_digits_follow_child_quadrants() {
+    let quadrant = Quadrant::new(0.0, 0.0, 1000.0);
+    let key = |x, y| get_key(&quadrant, &Coordinate::new(x, y));
+
+    assert_eq!(get_digit(key(100.0, 100.0), 0), 0);
+    assert_eq!(get_digit(key(900.0, 100.0), 0), 1);
+    assert_eq!(get_digit(key(100.0, 900.0), 0), 2);
+    assert_eq!(get_digit(key(900.0, 900.0), 0), 3);
+    // (600, 600) is in the sw child of the ne quadrant
+    assert_eq!(get_digit(key(600.0, 600.0), 1), 0);
+    assert!(key(100.0, 900.0) < key(900.0, 900.0));
+    // The centre is in the nw quadrant
+    assert_eq!(get_digit(key(500.0, 500.0), 0), 2);
+  }
+}
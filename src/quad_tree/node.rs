@@ -1,33 +1,112 @@
+use std::mem;
+
 use super::Body;
 use super::Coordinate;
 use super::Quadrant;
 use super::Vector;
+use super::bounds::Bounds;
+use super::morton;
+use super::opening_criterion::OpeningCriterion;
+use super::periodic::PeriodicDomain;
+use super::quadrupole::{Expansion, Quadrupole};
+use crate::float::Real;
+
+// Deepest a leaf can be and still split cleanly. Further down, a child's edge is within a few ulps of
+// the bodies and rounding can leave one outside every child.
+pub const DEPTH_LIMIT: usize = Real::MANTISSA_DIGITS as usize - 8;
+
+// When leaves split, see `system::LEAF_CAPACITY` and `system::MAX_DEPTH`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LeafLimits {
+  // Bodies a leaf holds before it splits
+  pub capacity: usize,
+  // Leaves this deep never split, so that coincident bodies end up sharing one
+  pub max_depth: usize,
+}
 
+impl Default for LeafLimits {
+  fn default() -> Self {
+    LeafLimits {
+      capacity: 1,
+      max_depth: DEPTH_LIMIT,
+    }
+  }
+}
+
+// A node as the `NodeArena` of its tree stores it, with its children as indices into the arena
 #[derive(Debug)]
-pub struct Node {
+pub struct ArenaNode {
   body: Option<Body>,
+  // About `body`, the centre of mass
+  quadrupole: Quadrupole,
+  bounds: Option<Bounds>,
   children: Option<Children>,
+  // Bodies of a leaf, summed directly by the walks
+  bucket: Vec<Body>,
   quadrant: Quadrant,
+  depth: usize,
+  limits: LeafLimits,
 }
 
-impl Node {
+impl ArenaNode {
   pub fn new(quadrant: Quadrant) -> Self {
-    Node {
+    ArenaNode::new_with_limits(quadrant, LeafLimits::default())
+  }
+
+  pub fn new_with_limits(quadrant: Quadrant, limits: LeafLimits) -> Self {
+    ArenaNode {
       body: None,
+      quadrupole: Quadrupole::default(),
+      bounds: None,
       quadrant,
       children: None,
+      bucket: Vec::new(),
+      depth: 0,
+      limits,
     }
   }
 
   pub fn reset(&mut self) {
     self.body = None;
+    self.quadrupole = Quadrupole::default();
+    self.bounds = None;
     self.children = None;
+    self.bucket.clear();
+  }
+
+  // Empty node over `quadrant`, keeping the bucket's allocation
+  fn reuse(&mut self, quadrant: Quadrant, limits: LeafLimits, depth: usize) {
+    self.reset();
+    self.quadrant = quadrant;
+    self.limits = limits;
+    self.depth = depth;
+  }
+
+  // Same as `clone_from`, keeping the bucket's allocation
+  fn copy_from(&mut self, other: &ArenaNode) {
+    self.body = other.body;
+    self.quadrupole = other.quadrupole;
+    self.bounds = other.bounds;
+    self.children = other.children;
+    self.bucket.clear();
+    self.bucket.extend_from_slice(&other.bucket);
+    self.quadrant = other.quadrant;
+    self.depth = other.depth;
+    self.limits = other.limits;
   }
 
   pub fn get_body(&self) -> &Option<Body> {
     &self.body
   }
 
+  pub fn get_quadrupole(&self) -> &Quadrupole {
+    &self.quadrupole
+  }
+
+  pub fn get_bounds(&self) -> &Option<Bounds> {
+    &self.bounds
+  }
+
   pub fn get_quadrant(&self) -> &Quadrant {
     &self.quadrant
   }
@@ -35,127 +114,638 @@ impl Node {
     &self.children
   }
 
-  pub fn insert(&mut self, body: Body) {
+  // Empty unless the node is a leaf
+  pub fn get_bucket(&self) -> &[Body] {
+    &self.bucket
+  }
+
+  // Adds `body` to the centre of mass, quadrupole and bounds of the node
+  fn add_body(&mut self, body: Body) {
     match self.body {
       Some(existing_body) => {
-        if self.children.is_none() {
-          self.init_children();
-          self.children.as_mut().unwrap().insert(existing_body);
-        }
-
-        self.children.as_mut().unwrap().insert(body);
-        self.body = Some(existing_body.add_body(&body));
+        let total_body = existing_body.add_body(&body);
+        let centre = total_body.get_coordinate();
+        let offset = |body: &Body| (
+          body.get_coordinate().get_x() - centre.get_x(),
+          body.get_coordinate().get_y() - centre.get_y(),
+        );
+        let (existing_dx, existing_dy) = offset(&existing_body);
+        let (dx, dy) = offset(&body);
+
+        self.quadrupole = self.quadrupole.shift(existing_body.get_mass(), existing_dx, existing_dy)
+          + Quadrupole::new(body.get_mass(), dx, dy);
+        self.bounds = self.bounds.map(|bounds| bounds.add_coordinate(body.get_coordinate()));
+        self.body = Some(total_body);
       }
       None => {
+        self.bounds = Some(Bounds::new(*body.get_coordinate()));
         self.body = Some(body);
       }
     }
   }
 
-  pub fn merge(&mut self, nodes: &mut Vec<Box<Self>>) {
-    let bodies: Vec<Option<Body>> = nodes.iter().map(|node| node.body).collect();
-    let mut nodes_drain = nodes.drain(..);
-    self.children = Some(Children {
-      ne: nodes_drain.next().unwrap(),
-      nw: nodes_drain.next().unwrap(),
-      se: nodes_drain.next().unwrap(),
-      sw: nodes_drain.next().unwrap(),
+  fn clear_aggregates(&mut self) {
+    self.body = None;
+    self.quadrupole = Quadrupole::default();
+    self.bounds = None;
+  }
+
+  // Sets the aggregates of the node from those of its `children`, in (ne, nw, se, sw) order
+  fn merge(&mut self, children: [&ArenaNode; 4]) {
+    // Only a refit leaves all the children empty
+    if children.iter().all(|node| node.body.is_none()) {
+      self.clear_aggregates();
+      return;
+    }
+    let bodies = [children[0].body, children[1].body, children[2].body, children[3].body];
+    let quadrupoles = [children[0].quadrupole, children[1].quadrupole, children[2].quadrupole, children[3].quadrupole];
+    self.bounds = children.iter().filter_map(|node| node.bounds).fold(None, |acc: Option<Bounds>, bounds| {
+      Some(acc.map_or(bounds, |acc| acc.add_bounds(&bounds)))
     });
 
-    self.merge_set_body(bodies);
+    self.merge_set_body(bodies, quadrupoles);
   }
 
-  fn merge_set_body(&mut self, bodies: Vec<Option<Body>>) {
-    let default_body = Body::new(0.0, Coordinate::new(0.0, 0.0));
-    let mass = bodies.iter().fold(0.0, |acc, body| {
-      acc + body.unwrap_or(default_body).get_mass()
-    });
-    let x = bodies.iter().fold(0.0, |acc, body| {
-      acc
-        + body.unwrap_or(default_body).get_coordinate().get_x()
-          * body.unwrap_or(default_body).get_mass()
+  fn merge_set_body(&mut self, bodies: [Option<Body>; 4], quadrupoles: [Quadrupole; 4]) {
+    let mass = bodies.iter().flatten().fold(0.0, |acc, body| acc + body.get_mass());
+    let x = bodies.iter().flatten().fold(0.0, |acc, body| {
+      acc + body.get_coordinate().get_x() * body.get_mass()
     }) / mass;
-    let y = bodies.iter().fold(0.0, |acc, body| {
-      acc
-        + body.unwrap_or(default_body).get_coordinate().get_y()
-          * body.unwrap_or(default_body).get_mass()
+    let y = bodies.iter().flatten().fold(0.0, |acc, body| {
+      acc + body.get_coordinate().get_y() * body.get_mass()
     }) / mass;
     let coordinate = Coordinate::new(x, y);
+    let velocity = bodies.iter().flatten().fold(Vector::new(0.0, 0.0), |acc, body| {
+      acc + *body.get_velocity() * body.get_mass()
+    }) / mass;
 
-    self.body = Some(Body::new(mass, coordinate));
+    let quadrupole = bodies.iter().zip(quadrupoles.iter()).fold(Quadrupole::default(), |acc, (body, quadrupole)| {
+      match body {
+        Some(body) => acc + quadrupole.shift(
+          body.get_mass(),
+          body.get_coordinate().get_x() - x,
+          body.get_coordinate().get_y() - y,
+        ),
+        None => acc,
+      }
+    });
+
+    let mut body = Body::new_aggregate(mass, coordinate);
+    body.set_velocity(velocity);
+    self.body = Some(body);
+    self.quadrupole = quadrupole;
   }
 
-  fn init_children(&mut self) {
-    let (ne_quad, nw_quad, se_quad, sw_quad) = self.quadrant.get_child_quadrants();
-    self.children = Some(Children {
-      ne: Box::new(Node::new(ne_quad)),
-      nw: Box::new(Node::new(nw_quad)),
-      se: Box::new(Node::new(se_quad)),
-      sw: Box::new(Node::new(sw_quad)),
-    });
+  // Force from the node's whole mass distribution as seen by a distant `body`
+  fn calculate_far_force_on(&self, self_body: &Body, body: &Body, expansion: Expansion) -> Vector {
+    let force = self_body.calculate_force_on(body);
+
+    // A leaf with a single body has no quadrupole
+    match expansion {
+      Expansion::Quadrupole => {
+        let dx = body.get_coordinate().get_x() - self_body.get_coordinate().get_x();
+        let dy = body.get_coordinate().get_y() - self_body.get_coordinate().get_y();
+
+        force + self.quadrupole.calculate_acceleration_at(dx, dy) * body.get_mass()
+      }
+      _ => force,
+    }
+  }
+
+  // Acceleration and jerk on a distant `body`. The quadrupole adds to the acceleration only, its jerk
+  // is neglected.
+  fn calculate_far_acceleration_and_jerk_on(&self, self_body: &Body, body: &Body, expansion: Expansion) -> (Vector, Vector) {
+    let (acceleration, jerk) = self_body.calculate_acceleration_and_jerk_on(body);
+
+    match expansion {
+      Expansion::Quadrupole => {
+        let dx = body.get_coordinate().get_x() - self_body.get_coordinate().get_x();
+        let dy = body.get_coordinate().get_y() - self_body.get_coordinate().get_y();
+
+        (acceleration + self.quadrupole.calculate_acceleration_at(dx, dy), jerk)
+      }
+      _ => (acceleration, jerk),
+    }
+  }
+
+  fn calculate_far_potential_energy_of(&self, self_body: &Body, body: &Body, expansion: Expansion) -> Real {
+    let energy = self_body.calculate_potential_energy_with(body);
+
+    match expansion {
+      Expansion::Quadrupole => {
+        let dx = body.get_coordinate().get_x() - self_body.get_coordinate().get_x();
+        let dy = body.get_coordinate().get_y() - self_body.get_coordinate().get_y();
+
+        energy + self.quadrupole.calculate_potential_at(dx, dy) * body.get_mass()
+      }
+      _ => energy,
+    }
+  }
+
+  // With a periodic domain the walk sees the image of `body` nearest to each node
+  fn get_image(self_body: &Body, body: &Body, domain: Option<&PeriodicDomain>) -> Body {
+    match domain {
+      Some(domain) => domain.get_nearest_image(body, self_body.get_coordinate()),
+      None => *body,
+    }
+  }
+
+  fn get_periodic_correction(self_body: &Body, body: &Body, domain: Option<&PeriodicDomain>) -> Vector {
+    match domain {
+      Some(domain) => domain.calculate_correction_on(self_body, body),
+      None => Vector::new(0.0, 0.0),
+    }
+  }
+
+  // Same walk as `calculate_net_force_on`, returning the potential energy of `body`. The periodic
+  // images beyond the nearest one aren't included.
+  pub fn calculate_potential_energy_of(
+    &self,
+    nodes: &NodeArena,
+    body: &Body,
+    criterion: OpeningCriterion,
+    expansion: Expansion,
+    domain: Option<&PeriodicDomain>,
+  ) -> Real {
+    match self.body {
+      Some(self_body) => {
+        if self_body.get_id() == body.get_id() {
+          return 0.0;
+        }
+        let image = &ArenaNode::get_image(&self_body, body, domain);
+
+        if criterion.accepts(self, &self_body, image) {
+          return self.calculate_far_potential_energy_of(&self_body, image, expansion);
+        }
+
+        match &self.children {
+          Some(children) => {
+            children
+              .get_all()
+              .iter()
+              .map(|&child| nodes.get(child).calculate_potential_energy_of(nodes, image, criterion, expansion, domain))
+              .sum()
+          }
+          None => self
+            .get_other_bodies(body)
+            .map(|other| other.calculate_potential_energy_with(&ArenaNode::get_image(other, body, domain)))
+            .sum(),
+        }
+      }
+      None => 0.0,
+    }
+  }
+
+  pub fn find_bodies_within(&self, nodes: &NodeArena, coordinate: &Coordinate, distance: Real, found: &mut Vec<Body>) {
+    if self.quadrant.get_distance_to(coordinate) > distance {
+      return;
+    }
+
+    match (&self.body, &self.children) {
+      (Some(_), Some(children)) => {
+        for &child in children.get_all().iter() {
+          nodes.get(child).find_bodies_within(nodes, coordinate, distance, found);
+        }
+      }
+      (Some(_), None) => {
+        found.extend(
+          self.bucket.iter().filter(|body| body.get_coordinate().get_distance_between(*coordinate) <= distance),
+        );
+      }
+      _ => {}
+    }
+  }
+
+  // Bodies in the bucket other than `body`, summed directly at the leaves
+  fn get_other_bodies<'a>(&'a self, body: &'a Body) -> impl Iterator<Item = &'a Body> {
+    self.bucket.iter().filter(move |other| other.get_id() != body.get_id())
   }
 
-  pub fn calculate_net_force_on(&self, body: &Body, theta: f32) -> Vector {
+  pub fn calculate_net_force_on(
+    &self,
+    nodes: &NodeArena,
+    body: &Body,
+    criterion: OpeningCriterion,
+    expansion: Expansion,
+    domain: Option<&PeriodicDomain>,
+  ) -> Vector {
     match self.body {
       Some(self_body) => {
         if self_body.get_id() == body.get_id() {
           return Vector::new(0.0, 0.0);
         }
+        let image = &ArenaNode::get_image(&self_body, body, domain);
 
-        let distance = self_body.get_distance_between(body);
-        let side_length = self.quadrant.get_length();
-        if side_length / distance < theta {
-          return self_body.calculate_force_on(body);
+        if criterion.accepts(self, &self_body, image) {
+          return self.calculate_far_force_on(&self_body, image, expansion)
+            + ArenaNode::get_periodic_correction(&self_body, image, domain);
         } else {
           return match &self.children {
             Some(children) => {
-              let Children { nw, ne, sw, se } = children;
-              let net_force = nw.calculate_net_force_on(body, theta)
-                + ne.calculate_net_force_on(body, theta)
-                + sw.calculate_net_force_on(body, theta)
-                + se.calculate_net_force_on(body, theta);
+              let Children { nw, ne, sw, se } = *children;
+              let net_force = nodes.get(nw).calculate_net_force_on(nodes, image, criterion, expansion, domain)
+                + nodes.get(ne).calculate_net_force_on(nodes, image, criterion, expansion, domain)
+                + nodes.get(sw).calculate_net_force_on(nodes, image, criterion, expansion, domain)
+                + nodes.get(se).calculate_net_force_on(nodes, image, criterion, expansion, domain);
 
               return net_force;
             }
-            None => self_body.calculate_force_on(body),
+            None => self.get_other_bodies(body).fold(Vector::new(0.0, 0.0), |net_force, other| {
+              let image = &ArenaNode::get_image(other, body, domain);
+              net_force + other.calculate_force_on(image) + ArenaNode::get_periodic_correction(other, image, domain)
+            }),
           };
         }
       }
       None => Vector::new(0.0, 0.0),
     }
   }
+
+  // Same walk as `calculate_net_force_on`, returning the acceleration and jerk on `body`
+  // The periodic correction adds to the acceleration only, its jerk is neglected
+  pub fn calculate_net_acceleration_and_jerk_on(
+    &self,
+    nodes: &NodeArena,
+    body: &Body,
+    criterion: OpeningCriterion,
+    expansion: Expansion,
+    domain: Option<&PeriodicDomain>,
+  ) -> (Vector, Vector) {
+    match self.body {
+      Some(self_body) => {
+        if self_body.get_id() == body.get_id() {
+          return (Vector::new(0.0, 0.0), Vector::new(0.0, 0.0));
+        }
+        let image = &ArenaNode::get_image(&self_body, body, domain);
+        let correction = |source: &Body, image: &Body| {
+          ArenaNode::get_periodic_correction(source, image, domain) / image.get_mass()
+        };
+
+        if criterion.accepts(self, &self_body, image) {
+          let (acceleration, jerk) = self.calculate_far_acceleration_and_jerk_on(&self_body, image, expansion);
+          return (acceleration + correction(&self_body, image), jerk);
+        }
+
+        let children = self.children.map(|children| children.get_all());
+        let (acceleration, jerk) = children.iter().flatten().fold(
+          (Vector::new(0.0, 0.0), Vector::new(0.0, 0.0)),
+          |(acceleration, jerk), &child| {
+            let (child_acceleration, child_jerk) =
+              nodes.get(child).calculate_net_acceleration_and_jerk_on(nodes, image, criterion, expansion, domain);
+            (acceleration + child_acceleration, jerk + child_jerk)
+          },
+        );
+
+        self.get_other_bodies(body).fold((acceleration, jerk), |(acceleration, jerk), other| {
+          let image = &ArenaNode::get_image(other, body, domain);
+          let (other_acceleration, other_jerk) = other.calculate_acceleration_and_jerk_on(image);
+          (acceleration + other_acceleration + correction(other, image), jerk + other_jerk)
+        })
+      }
+      None => (Vector::new(0.0, 0.0), Vector::new(0.0, 0.0)),
+    }
+  }
 }
 
-#[derive(Debug)]
+// Indices of the children in the `NodeArena`
+#[derive(Debug, Clone, Copy)]
 pub struct Children {
-  pub nw: Box<Node>,
-  pub ne: Box<Node>,
-  pub sw: Box<Node>,
-  pub se: Box<Node>,
+  pub nw: usize,
+  pub ne: usize,
+  pub sw: usize,
+  pub se: usize,
 }
 
 impl Children {
+  // In (nw, ne, sw, se) order, on the stack so that walking them allocates nothing
+  pub fn get_all(&self) -> [usize; 4] {
+    [self.nw, self.ne, self.sw, self.se]
+  }
+}
+
+// The nodes of a tree stored flat, the root first, with children as indices. Resetting keeps every
+// node and its bucket allocated for the next build, so rebuilding allocates nothing once the arena
+// has grown to the size of the tree.
+#[derive(Debug)]
+pub struct NodeArena {
+  nodes: Vec<ArenaNode>,
+  // Nodes in use, the rest are left from earlier builds
+  len: usize,
+  // Bodies of the Morton build with their keys, kept for the next one
+  sorted: Vec<(u64, Body)>,
+}
+
+impl NodeArena {
+  pub fn new(quadrant: Quadrant, limits: LeafLimits) -> Self {
+    NodeArena {
+      nodes: vec![ArenaNode::new_with_limits(quadrant, limits)],
+      len: 1,
+      sorted: Vec::new(),
+    }
+  }
+
+  // Leaves just an empty root over `quadrant`, `depth` below the root of the whole tree
+  pub fn reset(&mut self, quadrant: Quadrant, limits: LeafLimits, depth: usize) {
+    self.len = 0;
+    self.allocate(quadrant, limits, depth);
+  }
+
+  pub fn get_root(&self) -> &ArenaNode {
+    &self.nodes[0]
+  }
+
+  pub fn get(&self, index: usize) -> &ArenaNode {
+    &self.nodes[index]
+  }
+
+  // Index of an empty node, reusing one left from an earlier build when there is one
+  fn allocate(&mut self, quadrant: Quadrant, limits: LeafLimits, depth: usize) -> usize {
+    let index = self.len;
+    match self.nodes.get_mut(index) {
+      Some(node) => node.reuse(quadrant, limits, depth),
+      None => {
+        let mut node = ArenaNode::new_with_limits(quadrant, limits);
+        node.depth = depth;
+        self.nodes.push(node);
+      }
+    }
+    self.len += 1;
+
+    index
+  }
+
+  fn init_children(&mut self, index: usize) -> Children {
+    let (ne_quad, nw_quad, se_quad, sw_quad) = self.nodes[index].quadrant.get_child_quadrants();
+    let (limits, depth) = (self.nodes[index].limits, self.nodes[index].depth + 1);
+    let children = Children {
+      ne: self.allocate(ne_quad, limits, depth),
+      nw: self.allocate(nw_quad, limits, depth),
+      se: self.allocate(se_quad, limits, depth),
+      sw: self.allocate(sw_quad, limits, depth),
+    };
+    self.nodes[index].children = Some(children);
+
+    children
+  }
+
+  pub fn get_containing_child_node(&self, children: &Children, coordinate: &Coordinate) -> Option<usize> {
+    children.get_all().iter().copied().find(|&child| self.nodes[child].quadrant.contains(coordinate))
+  }
+
   pub fn insert(&mut self, body: Body) {
-    let child_node = self.get_containing_child_node(&body.get_coordinate());
-    match child_node {
-      Some(child_node) => child_node.insert(body),
+    self.insert_at(0, body);
+  }
+
+  fn insert_at(&mut self, index: usize, body: Body) {
+    self.nodes[index].add_body(body);
+
+    match self.nodes[index].children {
+      Some(children) => self.insert_into(&children, body),
+      None => {
+        let node = &mut self.nodes[index];
+        node.bucket.push(body);
+        if node.bucket.len() > node.limits.capacity && node.depth < node.limits.max_depth {
+          let children = self.init_children(index);
+          // Taken out and put back empty, so that the leaf's allocation is kept for the next build
+          let mut bucket = mem::take(&mut self.nodes[index].bucket);
+          for body in bucket.drain(..) {
+            self.insert_into(&children, body);
+          }
+          self.nodes[index].bucket = bucket;
+        }
+      }
+    }
+  }
+
+  fn insert_into(&mut self, children: &Children, body: Body) {
+    match self.get_containing_child_node(children, body.get_coordinate()) {
+      Some(child) => self.insert_at(child, body),
       None => panic!("body doesn't belong to any quadrant"),
     }
   }
 
-  pub fn get_containing_child_node(&mut self, coordinate: &Coordinate) -> Option<&mut Box<Node>> {
-    let Children { nw, ne, sw, se } = self;
-    let containing_child_node = vec![nw, ne, sw, se]
-      .into_iter()
-      .find(|child| child.quadrant.contains(coordinate));
+  // Makes the roots of `subtrees` the children of the root, in (ne, nw, se, sw) order, and merges their
+  // aggregates into it. The subtrees are copied in, reusing the nodes already here.
+  pub fn merge(&mut self, subtrees: [&NodeArena; 4]) {
+    let root = &self.nodes[0];
+    let (quadrant, limits, depth) = (root.quadrant, root.limits, root.depth);
+    self.reset(quadrant, limits, depth);
+
+    let children = self.init_children(0);
+    for (&index, subtree) in [children.ne, children.nw, children.se, children.sw].iter().zip(subtrees.iter()) {
+      self.graft(index, subtree);
+    }
+    self.merge_children(0);
+  }
+
+  // Fills the empty arena with `bodies` sorted by their Morton keys. The bodies of every node are then
+  // a contiguous range, cut into the children's ranges by the next digit of the keys, and the nodes'
+  // aggregates are merged bottom-up. The tree has the shape of one built by inserting, apart from
+  // bodies within rounding of the edge between two children, and the same aggregates up to rounding.
+  pub fn build_morton(&mut self, bodies: &[Body]) {
+    let quadrant = self.nodes[0].quadrant;
+    self.sorted.clear();
+    self.sorted.extend(bodies.iter().map(|body| (morton::get_key(&quadrant, body.get_coordinate()), *body)));
+    self.sorted.sort_by_key(|&(key, _)| key);
+
+    self.build_range(0, 0, self.sorted.len());
+  }
+
+  fn build_range(&mut self, index: usize, start: usize, end: usize) {
+    let node = &self.nodes[index];
+    // The keys can't tell bodies apart any deeper
+    if end - start <= node.limits.capacity || node.depth >= node.limits.max_depth.min(morton::LEVELS) {
+      let NodeArena { nodes, sorted, .. } = self;
+      for &(_, body) in &sorted[start..end] {
+        nodes[index].add_body(body);
+        nodes[index].bucket.push(body);
+      }
+      return;
+    }
 
-    return containing_child_node;
+    let depth = node.depth;
+    let children = self.init_children(index);
+    let mut from = start;
+    for (digit, &child) in [children.sw, children.se, children.nw, children.ne].iter().enumerate() {
+      let to = from + self.sorted[from..end].partition_point(|&(key, _)| morton::get_digit(key, depth) <= digit);
+      self.build_range(child, from, to);
+      from = to;
+    }
+    self.merge_children(index);
+  }
+
+  // Puts `bodies` in the leaves that now contain them and recomputes the aggregates bottom-up, keeping
+  // the nodes as they are. Leaves can hold more than their capacity until the next build.
+  pub fn refit(&mut self, bodies: &[Body]) {
+    self.nodes[..self.len].iter_mut().for_each(|node| node.bucket.clear());
+    for body in bodies {
+      let mut index = 0;
+      while let Some(children) = self.nodes[index].children {
+        index = self
+          .get_containing_child_node(&children, body.get_coordinate())
+          .expect("body doesn't belong to any quadrant");
+      }
+      self.nodes[index].bucket.push(*body);
+    }
+
+    // Every build allocates children after their parent
+    for index in (0..self.len).rev() {
+      match self.nodes[index].children {
+        Some(_) => self.merge_children(index),
+        None => {
+          let node = &mut self.nodes[index];
+          node.clear_aggregates();
+          let bucket = mem::take(&mut node.bucket);
+          bucket.iter().for_each(|body| node.add_body(*body));
+          node.bucket = bucket;
+        }
+      }
+    }
+  }
+
+  // Copies `subtree` in with its root at `index`, reusing the nodes already there
+  fn graft(&mut self, index: usize, subtree: &NodeArena) {
+    // The subtree's other nodes follow the ones in use, in the same order
+    let offset = self.len - 1;
+    let place = |i: usize| if i == 0 { index } else { offset + i };
+    for i in 0..subtree.len {
+      let target = place(i);
+      if target == self.nodes.len() {
+        self.nodes.push(ArenaNode::new(subtree.nodes[i].quadrant));
+      }
+      self.nodes[target].copy_from(&subtree.nodes[i]);
+      self.nodes[target].children = subtree.nodes[i].children.map(|Children { nw, ne, sw, se }| Children {
+        nw: place(nw),
+        ne: place(ne),
+        sw: place(sw),
+        se: place(se),
+      });
+    }
+    self.len += subtree.len - 1;
+  }
+
+  fn merge_children(&mut self, index: usize) {
+    let Children { nw, ne, sw, se } = self.nodes[index].children.expect("only split nodes are merged");
+    let mut node = mem::replace(&mut self.nodes[index], ArenaNode::new(Quadrant::new(0.0, 0.0, 0.0)));
+    node.merge([&self.nodes[ne], &self.nodes[nw], &self.nodes[se], &self.nodes[sw]]);
+    self.nodes[index] = node;
+  }
+}
+
+// The root of a tree, which owns the arena its nodes are stored in. Inserting and the walks start from
+// the root and borrow the arena themselves.
+#[derive(Debug)]
+pub struct Node {
+  nodes: NodeArena,
+}
+
+impl Node {
+  pub fn new(quadrant: Quadrant) -> Self {
+    Node::new_with_limits(quadrant, LeafLimits::default())
+  }
+
+  pub fn new_with_limits(quadrant: Quadrant, limits: LeafLimits) -> Self {
+    Node {
+      nodes: NodeArena::new(quadrant, limits),
+    }
+  }
+
+  pub fn reset(&mut self) {
+    let root = self.nodes.get_root();
+    let (quadrant, limits, depth) = (root.quadrant, root.limits, root.depth);
+    self.reuse(quadrant, limits, depth);
+  }
+
+  // Empty root over `quadrant`, keeping the nodes of the arena allocated for the next build
+  pub fn reuse(&mut self, quadrant: Quadrant, limits: LeafLimits, depth: usize) {
+    self.nodes.reset(quadrant, limits, depth);
+  }
+
+  pub fn get_nodes(&self) -> &NodeArena {
+    &self.nodes
+  }
+
+  pub fn get_body(&self) -> &Option<Body> {
+    self.nodes.get_root().get_body()
+  }
+
+  pub fn get_quadrant(&self) -> &Quadrant {
+    self.nodes.get_root().get_quadrant()
+  }
+
+  pub fn get_limits(&self) -> LeafLimits {
+    self.nodes.get_root().limits
+  }
+
+  pub fn get_depth(&self) -> usize {
+    self.nodes.get_root().depth
+  }
+
+  pub fn insert(&mut self, body: Body) {
+    self.nodes.insert(body);
+  }
+
+  pub fn build_morton(&mut self, bodies: &[Body]) {
+    self.nodes.build_morton(bodies);
+  }
+
+  pub fn refit(&mut self, bodies: &[Body]) {
+    self.nodes.refit(bodies);
+  }
+
+  // Makes `nodes` the children of this one, in (ne, nw, se, sw) order, taking them out of the vector
+  #[allow(dead_code, clippy::vec_box)]
+  pub fn merge(&mut self, nodes: &mut Vec<Box<Self>>) {
+    match nodes.as_slice() {
+      [ne, nw, se, sw] => self.merge_nodes([ne, nw, se, sw]),
+      _ => panic!("a node merges four children"),
+    }
+    nodes.clear();
+  }
+
+  // Same as `merge`, copying the children in and leaving them as they are
+  pub fn merge_nodes(&mut self, nodes: [&Node; 4]) {
+    self.nodes.merge([&nodes[0].nodes, &nodes[1].nodes, &nodes[2].nodes, &nodes[3].nodes]);
+  }
+
+  pub fn calculate_potential_energy_of(
+    &self,
+    body: &Body,
+    criterion: OpeningCriterion,
+    expansion: Expansion,
+    domain: Option<&PeriodicDomain>,
+  ) -> Real {
+    self.nodes.get_root().calculate_potential_energy_of(&self.nodes, body, criterion, expansion, domain)
+  }
+
+  pub fn find_bodies_within(&self, coordinate: &Coordinate, distance: Real, found: &mut Vec<Body>) {
+    self.nodes.get_root().find_bodies_within(&self.nodes, coordinate, distance, found);
+  }
+
+  pub fn calculate_net_force_on(
+    &self,
+    body: &Body,
+    criterion: OpeningCriterion,
+    expansion: Expansion,
+    domain: Option<&PeriodicDomain>,
+  ) -> Vector {
+    self.nodes.get_root().calculate_net_force_on(&self.nodes, body, criterion, expansion, domain)
+  }
+
+  pub fn calculate_net_acceleration_and_jerk_on(
+    &self,
+    body: &Body,
+    criterion: OpeningCriterion,
+    expansion: Expansion,
+    domain: Option<&PeriodicDomain>,
+  ) -> (Vector, Vector) {
+    self.nodes.get_root().calculate_net_acceleration_and_jerk_on(&self.nodes, body, criterion, expansion, domain)
   }
 }
 
 #[cfg(test)]
 mod tests {
-  use super::{Body, Coordinate, Node, Quadrant};
+  use super::{Body, Coordinate, Expansion, LeafLimits, Node, OpeningCriterion, Quadrant, Real, Vector};
 
   #[test]
   fn insert_on_node_with_no_body() {
@@ -163,9 +753,9 @@ mod tests {
     let body = Body::new(10.0, Coordinate::new(400.0, 600.0));
     node.insert(body);
 
-    assert_eq!(node.body.unwrap().get_mass(), 10.0);
-    assert_eq!(node.body.unwrap().get_coordinate().get_x(), 400.0);
-    assert_eq!(node.body.unwrap().get_coordinate().get_y(), 600.0);
+    assert_eq!(node.get_body().unwrap().get_mass(), 10.0);
+    assert_eq!(node.get_body().unwrap().get_coordinate().get_x(), 400.0);
+    assert_eq!(node.get_body().unwrap().get_coordinate().get_y(), 600.0);
   }
 
   #[test]
@@ -176,15 +766,16 @@ mod tests {
     node.insert(body_1);
     node.insert(body_2);
 
-    assert_eq!(node.body.unwrap().get_mass(), 20.0);
-    assert_eq!(node.body.unwrap().get_coordinate().get_x(), 650.0);
-    assert_eq!(node.body.unwrap().get_coordinate().get_y(), 600.0);
+    assert_eq!(node.get_body().unwrap().get_mass(), 20.0);
+    assert_eq!(node.get_body().unwrap().get_coordinate().get_x(), 650.0);
+    assert_eq!(node.get_body().unwrap().get_coordinate().get_y(), 600.0);
 
-    let children = node.children.unwrap();
-    let sw_body = children.sw.body;
-    let se_body = children.se.body;
-    let nw_body = children.nw.body;
-    let ne_body = children.ne.body;
+    let nodes = node.get_nodes();
+    let children = nodes.get_root().children.unwrap();
+    let sw_body = nodes.get(children.sw).body;
+    let se_body = nodes.get(children.se).body;
+    let nw_body = nodes.get(children.nw).body;
+    let ne_body = nodes.get(children.ne).body;
 
     assert_eq!(sw_body.unwrap().get_mass(), 10.0);
     assert_eq!(sw_body.unwrap().get_coordinate().get_x(), 400.0);
@@ -206,31 +797,143 @@ mod tests {
     node.insert(body_1);
     node.insert(body_2);
 
-    assert_eq!(node.body.unwrap().get_mass(), 20.0);
-    assert_eq!(node.body.unwrap().get_coordinate().get_x(), 235.0);
-    assert_eq!(node.body.unwrap().get_coordinate().get_y(), 200.0);
+    assert_eq!(node.get_body().unwrap().get_mass(), 20.0);
+    assert_eq!(node.get_body().unwrap().get_coordinate().get_x(), 235.0);
+    assert_eq!(node.get_body().unwrap().get_coordinate().get_y(), 200.0);
 
-    let children = node.children.unwrap();
+    let nodes = node.get_nodes();
+    let children = nodes.get_root().children.unwrap();
 
-    assert!(children.ne.body.is_none());
-    assert!(children.se.body.is_none());
-    assert!(children.sw.body.is_none());
+    assert!(nodes.get(children.ne).body.is_none());
+    assert!(nodes.get(children.se).body.is_none());
+    assert!(nodes.get(children.sw).body.is_none());
 
-    let nw_body = children.nw.body;
+    let nw_body = nodes.get(children.nw).body;
     assert_eq!(nw_body.unwrap().get_mass(), 20.0);
     assert_eq!(nw_body.unwrap().get_coordinate().get_x(), 235.0);
     assert_eq!(nw_body.unwrap().get_coordinate().get_y(), 200.0);
 
-    let nw_children = children.nw.children.unwrap();
-    assert!(nw_children.nw.body.is_some());
-    assert!(nw_children.ne.body.is_some());
+    let nw_children = nodes.get(children.nw).children.unwrap();
+    let (nw_nw_body, nw_ne_body) = (nodes.get(nw_children.nw).body, nodes.get(nw_children.ne).body);
+    assert!(nw_nw_body.is_some());
+    assert!(nw_ne_body.is_some());
 
-    assert_eq!(nw_children.nw.body.unwrap().get_mass(), 10.0);
-    assert_eq!(nw_children.nw.body.unwrap().get_coordinate().get_x(), 200.0);
-    assert_eq!(nw_children.nw.body.unwrap().get_coordinate().get_y(), 200.0);
+    assert_eq!(nw_nw_body.unwrap().get_mass(), 10.0);
+    assert_eq!(nw_nw_body.unwrap().get_coordinate().get_x(), 200.0);
+    assert_eq!(nw_nw_body.unwrap().get_coordinate().get_y(), 200.0);
+
+    assert_eq!(nw_ne_body.unwrap().get_mass(), 10.0);
+    assert_eq!(nw_ne_body.unwrap().get_coordinate().get_x(), 270.0);
+    assert_eq!(nw_ne_body.unwrap().get_coordinate().get_y(), 200.0);
+  }
+
+  #[test]
+  fn coincident_bodies_share_a_leaf_at_max_depth() {
+    let mut node = Node::new_with_limits(Quadrant::new(0.0, 0.0, 1000.0), LeafLimits { capacity: 1, max_depth: 6 });
+    (0..3).for_each(|_| node.insert(Body::new(10.0, Coordinate::new(300.0, 300.0))));
+
+    let nodes = node.get_nodes();
+    let mut leaf = nodes.get_root();
+    while let Some(children) = &leaf.children {
+      leaf = children.get_all().iter().map(|&child| nodes.get(child)).find(|child| child.body.is_some()).unwrap();
+    }
+
+    assert_eq!(leaf.depth, 6);
+    assert_eq!(leaf.get_bucket().len(), 3);
+    assert_eq!(node.get_body().unwrap().get_mass(), 30.0);
+  }
 
-    assert_eq!(nw_children.ne.body.unwrap().get_mass(), 10.0);
-    assert_eq!(nw_children.ne.body.unwrap().get_coordinate().get_x(), 270.0);
-    assert_eq!(nw_children.ne.body.unwrap().get_coordinate().get_y(), 200.0);
+  #[test]
+  fn bodies_ulps_apart_stop_splitting_at_the_default_depth() {
+    let mut node = Node::new(Quadrant::new(0.0, 0.0, 1000.0));
+    let ulps = |value: Real, count| Real::from_bits(value.to_bits() + count);
+    let (x, y) = (ulps(98.68, 1), ulps(98.68, 3));
+    node.insert(Body::new(10.0, Coordinate::new(98.68, 98.68)));
+    node.insert(Body::new(10.0, Coordinate::new(x, y)));
+    node.insert(Body::new(10.0, Coordinate::new(y, x)));
+
+    assert_eq!(node.get_body().unwrap().get_mass(), 30.0);
+  }
+
+  #[test]
+  fn bucket_is_summed_directly() {
+    let mut node = Node::new_with_limits(Quadrant::new(0.0, 0.0, 1000.0), LeafLimits { capacity: 4, max_depth: 32 });
+    let bodies = [
+      Body::new(10.0, Coordinate::new(100.0, 100.0)),
+      Body::new(40.0, Coordinate::new(180.0, 130.0)),
+      Body::new(25.0, Coordinate::new(120.0, 210.0)),
+    ];
+    bodies.iter().for_each(|body| node.insert(*body));
+    let target = Body::new(1.0, Coordinate::new(140.0, 150.0));
+
+    let exact = bodies.iter().fold(Vector::new(0.0, 0.0), |acc, body| acc + body.calculate_force_on(&target));
+    let force = node.calculate_net_force_on(&target, OpeningCriterion::BarnesHut { theta: 0.0 }, Expansion::Monopole, None);
+
+    assert!(node.get_nodes().get_root().children.is_none());
+    assert_eq!(force, exact);
+  }
+
+  #[test]
+  fn quadrupole_improves_far_force() {
+    let mut node = Node::new(Quadrant::new(0.0, 0.0, 1000.0));
+    let bodies = [
+      Body::new(10.0, Coordinate::new(100.0, 100.0)),
+      Body::new(40.0, Coordinate::new(180.0, 130.0)),
+      Body::new(25.0, Coordinate::new(120.0, 210.0)),
+    ];
+    bodies.iter().for_each(|body| node.insert(*body));
+    let root = node.get_nodes().get_root();
+    let target = Body::new(1.0, Coordinate::new(900.0, 700.0));
+
+    let exact = bodies.iter().fold(Vector::new(0.0, 0.0), |acc, body| acc + body.calculate_force_on(&target));
+    let error = |expansion| {
+      (root.calculate_far_force_on(&root.body.unwrap(), &target, expansion) - exact).get_norm()
+    };
+
+    assert!(error(Expansion::Quadrupole) < 0.5 * error(Expansion::Monopole));
+  }
+
+  #[test]
+  fn reset_reuses_the_nodes() {
+    let mut node = Node::new_with_limits(Quadrant::new(0.0, 0.0, 1000.0), LeafLimits { capacity: 2, max_depth: 32 });
+    let bodies: Vec<Body> = (0..50).map(|i| Body::new(1.0, Coordinate::new(10.0 + 19.0 * i as Real, 500.0))).collect();
+    bodies.iter().for_each(|body| node.insert(*body));
+    let (len, allocated) = (node.nodes.len, node.nodes.nodes.len());
+
+    node.reset();
+    assert!(node.get_body().is_none());
+    bodies.iter().for_each(|body| node.insert(*body));
+
+    assert_eq!(node.nodes.len, len);
+    assert_eq!(node.nodes.nodes.len(), allocated);
+  }
+
+  #[test]
+  fn merge_makes_the_nodes_children() {
+    let quadrant = Quadrant::new(0.0, 0.0, 1000.0);
+    let bodies: Vec<Body> = (0..40)
+      .map(|i| Body::new(1.0, Coordinate::new(30.0 + 23.0 * i as Real, 970.0 - 19.0 * i as Real)))
+      .collect();
+    let mut serial = Node::new(quadrant);
+    bodies.iter().for_each(|body| serial.insert(*body));
+
+    let (ne, nw, se, sw) = quadrant.get_child_quadrants();
+    let mut children: Vec<Box<Node>> = [ne, nw, se, sw]
+      .iter()
+      .map(|&quadrant| {
+        let mut child = Node::new(quadrant);
+        child.reuse(quadrant, LeafLimits::default(), 1);
+        bodies.iter().filter(|body| quadrant.contains(body.get_coordinate())).for_each(|body| child.insert(*body));
+        Box::new(child)
+      })
+      .collect();
+    let mut node = Node::new(quadrant);
+    node.merge(&mut children);
+
+    assert!(children.is_empty());
+    assert_eq!(node.nodes.len, serial.nodes.len);
+    let (merged, expected) = (node.get_body().unwrap(), serial.get_body().unwrap());
+    assert!((merged.get_mass() - expected.get_mass()).abs() < 1e-4);
+    assert!(merged.get_coordinate().get_distance_between(*expected.get_coordinate()) < 1e-3);
   }
 }
@@ -0,0 +1,93 @@
+use super::Body;
+use super::node::ArenaNode;
+use crate::system::G;
+use crate::float::Real;
+
+// Decides whether a node is far enough from a body to be replaced by its multipole expansion,
+// see `system::OPENING_CRITERION`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpeningCriterion {
+  // Quadrant side length over distance to the centre of mass
+  BarnesHut { theta: Real },
+  // As `BarnesHut`, with the size of the box around the node's bodies instead of its quadrant
+  BoundingBox { theta: Real },
+  // Salmon & Warren (1994) bound on the absolute acceleration error of the monopole
+  SalmonWarren { tolerance: Real },
+  // GADGET: monopole error estimate against a fraction of the body's acceleration last step
+  Relative { alpha: Real },
+}
+
+impl OpeningCriterion {
+  pub fn accepts(&self, node: &ArenaNode, node_body: &Body, body: &Body) -> bool {
+    let distance = node_body.get_distance_between(body);
+
+    match *self {
+      OpeningCriterion::BarnesHut { theta } => node.get_quadrant().get_length() / distance < theta,
+      OpeningCriterion::BoundingBox { theta } => match node.get_bounds() {
+        Some(bounds) => bounds.get_size() / distance < theta,
+        None => false,
+      },
+      OpeningCriterion::SalmonWarren { tolerance } => match node.get_bounds() {
+        Some(bounds) => {
+          let max_offset = bounds.get_max_distance_from(node_body.get_coordinate());
+          // The in-plane trace of the quadrupole is sum m * |d|^2
+          let second_moment = node.get_quadrupole().get_trace();
+          let critical_distance = max_offset / 2.0
+            + (max_offset.powi(2) / 4.0 + (3.0 * G * second_moment / tolerance).sqrt()).sqrt();
+
+          distance > critical_distance
+        }
+        None => false,
+      },
+      OpeningCriterion::Relative { alpha } => {
+        let magnitude = body.get_acceleration().get_norm();
+        let side_length = node.get_quadrant().get_length();
+        // Bodies inside the node, or without an acceleration from a previous step, always open it
+        if magnitude == 0.0 || node.get_quadrant().get_distance_to(body.get_coordinate()) == 0.0 {
+          return false;
+        }
+
+        G * node_body.get_mass() / distance.powi(2) * (side_length / distance).powi(2) <= alpha * magnitude
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Body, OpeningCriterion};
+  use crate::quad_tree::node::{LeafLimits, NodeArena};
+  use crate::body::coordinate::Coordinate;
+  use crate::quad_tree::quadrant::Quadrant;
+  use crate::vector::Vector;
+
+  #[test]
+  fn test_bounding_box_uses_body_extent() {
+    // Two close bodies in a large quadrant
+    let mut nodes = NodeArena::new(Quadrant::new(0.0, 0.0, 1000.0), LeafLimits::default());
+    nodes.insert(Body::new(10.0, Coordinate::new(10.0, 10.0)));
+    nodes.insert(Body::new(10.0, Coordinate::new(20.0, 20.0)));
+    let node = nodes.get_root();
+    let node_body = node.get_body().unwrap();
+    let body = Body::new(1.0, Coordinate::new(500.0, 500.0));
+
+    assert!(!OpeningCriterion::BarnesHut { theta: 0.5 }.accepts(node, &node_body, &body));
+    assert!(OpeningCriterion::BoundingBox { theta: 0.5 }.accepts(node, &node_body, &body));
+  }
+
+  #[test]
+  fn test_relative_needs_acceleration() {
+    let mut nodes = NodeArena::new(Quadrant::new(0.0, 0.0, 100.0), LeafLimits::default());
+    nodes.insert(Body::new(10.0, Coordinate::new(10.0, 10.0)));
+    nodes.insert(Body::new(10.0, Coordinate::new(20.0, 20.0)));
+    let node = nodes.get_root();
+    let node_body = node.get_body().unwrap();
+    let mut body = Body::new(1.0, Coordinate::new(900.0, 900.0));
+    let criterion = OpeningCriterion::Relative { alpha: 0.005 };
+
+    assert!(!criterion.accepts(node, &node_body, &body));
+    body.set_acceleration(Vector::new(1.0, 0.0));
+    assert!(criterion.accepts(node, &node_body, &body));
+  }
+}
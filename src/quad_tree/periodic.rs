@@ -0,0 +1,178 @@
+use std::f64::consts::PI;
+
+use super::Body;
+use super::Coordinate;
+use super::Quadrant;
+use super::Vector;
+use crate::system::G;
+use crate::float::{to_f64, Real};
+
+// Points per axis of the correction table, which covers a quarter of the box
+const GRID_SIZE: usize = 33;
+// Lattice vectors summed in real space and in Fourier space, per axis either side of zero
+const REAL_IMAGES: i32 = 4;
+const FOURIER_IMAGES: i32 = 4;
+
+// Complementary error function, Abramowitz & Stegun 7.1.26 (absolute error below 1.5e-7)
+fn erfc(x: f64) -> f64 {
+  let t = 1.0 / (1.0 + 0.3275911 * x);
+  let polynomial = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+
+  polynomial * (-x * x).exp()
+}
+
+// Acceleration (G = m = 1) at offset (x, y) from a source repeated on a square lattice of side `length`,
+// by Ewald summation of the in-plane 1 / r kernel, minus the direct pull of the source itself
+fn calculate_ewald_correction(x: f64, y: f64, length: f64) -> (f64, f64) {
+  if x == 0.0 && y == 0.0 {
+    return (0.0, 0.0);
+  }
+
+  let alpha = 2.0 / length;
+  let (mut ax, mut ay) = (0.0, 0.0);
+
+  for i in -REAL_IMAGES..=REAL_IMAGES {
+    for j in -REAL_IMAGES..=REAL_IMAGES {
+      let (dx, dy) = (x + i as f64 * length, y + j as f64 * length);
+      let distance = (dx.powi(2) + dy.powi(2)).sqrt();
+      let magnitude = erfc(alpha * distance) / distance.powi(2)
+        + 2.0 * alpha / PI.sqrt() * (-(alpha * distance).powi(2)).exp() / distance;
+      ax -= magnitude * dx / distance;
+      ay -= magnitude * dy / distance;
+    }
+  }
+
+  for i in -FOURIER_IMAGES..=FOURIER_IMAGES {
+    for j in -FOURIER_IMAGES..=FOURIER_IMAGES {
+      if i == 0 && j == 0 {
+        continue;
+      }
+      let (kx, ky) = (2.0 * PI * i as f64 / length, 2.0 * PI * j as f64 / length);
+      let k = (kx.powi(2) + ky.powi(2)).sqrt();
+      let magnitude = 2.0 * PI / length.powi(2) * (kx * x + ky * y).sin() * erfc(k / (2.0 * alpha)) / k;
+      ax -= magnitude * kx;
+      ay -= magnitude * ky;
+    }
+  }
+
+  let distance = (x.powi(2) + y.powi(2)).sqrt();
+  (ax + x / distance.powi(3), ay + y / distance.powi(3))
+}
+
+// Square domain whose opposite edges are joined. Bodies wrap back inside, offsets take the
+// nearest periodic image and an Ewald correction adds the pull of all the other images.
+#[derive(Debug, Clone)]
+pub struct PeriodicDomain {
+  quadrant: Quadrant,
+  // Correction for offsets in [0, length / 2] along both axes, row major in x
+  corrections: Vec<(Real, Real)>,
+}
+
+impl PeriodicDomain {
+  pub fn new(quadrant: Quadrant) -> Self {
+    let length = to_f64(quadrant.get_length());
+    let spacing = 0.5 * length / (GRID_SIZE - 1) as f64;
+    let corrections = (0..GRID_SIZE)
+      .flat_map(|j| (0..GRID_SIZE).map(move |i| (i, j)))
+      .map(|(i, j)| {
+        let (x, y) = calculate_ewald_correction(i as f64 * spacing, j as f64 * spacing, length);
+        (x as Real, y as Real)
+      })
+      .collect();
+
+    PeriodicDomain {
+      quadrant,
+      corrections,
+    }
+  }
+
+  fn get_nearest_offset(&self, offset: Real) -> Real {
+    let length = self.quadrant.get_length();
+    offset - length * (offset / length).round()
+  }
+
+  // Brings `body` back inside the domain
+  pub fn wrap(&self, body: &mut Body) {
+    wrap(&self.quadrant, body);
+  }
+
+  // The image of `body` closest to `coordinate`
+  pub fn get_nearest_image(&self, body: &Body, coordinate: &Coordinate) -> Body {
+    let dx = self.get_nearest_offset(body.get_coordinate().get_x() - coordinate.get_x());
+    let dy = self.get_nearest_offset(body.get_coordinate().get_y() - coordinate.get_y());
+    let mut image = *body;
+    image.set_coordinate(Coordinate::new(coordinate.get_x() + dx, coordinate.get_y() + dy));
+    image
+  }
+
+  // Force on `body` from all the periodic images of `source` other than the nearest one,
+  // `body` being that nearest image already
+  pub fn calculate_correction_on(&self, source: &Body, body: &Body) -> Vector {
+    let dx = body.get_coordinate().get_x() - source.get_coordinate().get_x();
+    let dy = body.get_coordinate().get_y() - source.get_coordinate().get_y();
+
+    // Bilinear interpolation in the table, which is odd along its own axis and even along the other
+    let spacing = 0.5 * self.quadrant.get_length() / (GRID_SIZE - 1) as Real;
+    let (u, v) = ((dx.abs() / spacing).min((GRID_SIZE - 1) as Real), (dy.abs() / spacing).min((GRID_SIZE - 1) as Real));
+    let (i, j) = ((u as usize).min(GRID_SIZE - 2), (v as usize).min(GRID_SIZE - 2));
+    let (s, t) = (u - i as Real, v - j as Real);
+    let at = |i: usize, j: usize| self.corrections[j * GRID_SIZE + i];
+    let interpolate = |pick: fn((Real, Real)) -> Real| {
+      pick(at(i, j)) * (1.0 - s) * (1.0 - t)
+        + pick(at(i + 1, j)) * s * (1.0 - t)
+        + pick(at(i, j + 1)) * (1.0 - s) * t
+        + pick(at(i + 1, j + 1)) * s * t
+    };
+
+    Vector::new(
+      interpolate(|(x, _)| x) * dx.signum(),
+      interpolate(|(_, y)| y) * dy.signum(),
+    ) * (G * source.get_mass() * body.get_mass())
+  }
+}
+
+// Brings `body` back inside `quadrant` through the opposite wall, in the plane. The previous
+// coordinate moves with it so that rendering doesn't interpolate across the box.
+pub fn wrap(quadrant: &Quadrant, body: &mut Body) {
+  let length = quadrant.get_length();
+  let coordinate = *body.get_coordinate();
+  let x = quadrant.get_x() + (coordinate.get_x() - quadrant.get_x()).rem_euclid(length);
+  let y = quadrant.get_y() + (coordinate.get_y() - quadrant.get_y()).rem_euclid(length);
+  let (dx, dy) = (x - coordinate.get_x(), y - coordinate.get_y());
+  if dx == 0.0 && dy == 0.0 {
+    return;
+  }
+
+  let previous = *body.get_previous_coordinate();
+  body.set_coordinate(Coordinate::new_3d(x, y, coordinate.get_z()));
+  body.set_previous_coordinate(Coordinate::new_3d(previous.get_x() + dx, previous.get_y() + dy, previous.get_z()));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{calculate_ewald_correction, Body, Coordinate, PeriodicDomain, Quadrant};
+
+  #[test]
+  fn test_ewald_correction_cancels_at_symmetric_points() {
+    let length = 100.0;
+    // Half way to the next image along an axis, and at the centre of the cell, the pulls balance
+    for &(x, y) in &[(50.0_f64, 0.0_f64), (50.0, 50.0), (0.0, 50.0)] {
+      let (cx, cy) = calculate_ewald_correction(x, y, length);
+      let distance = (x * x + y * y).sqrt();
+      assert!((cx - x / distance.powi(3)).abs() < 1e-6);
+      assert!((cy - y / distance.powi(3)).abs() < 1e-6);
+    }
+  }
+
+  #[test]
+  fn test_wrap_and_nearest_image() {
+    let domain = PeriodicDomain::new(Quadrant::new(0.0, 0.0, 100.0));
+    let mut body = Body::new(1.0, Coordinate::new(105.0, -10.0));
+    domain.wrap(&mut body);
+
+    assert_eq!(*body.get_coordinate(), Coordinate::new(5.0, 90.0));
+
+    let image = domain.get_nearest_image(&body, &Coordinate::new(95.0, 10.0));
+    assert_eq!(*image.get_coordinate(), Coordinate::new(105.0, -10.0));
+  }
+}
@@ -1,14 +1,15 @@
 use super::Coordinate;
+use crate::float::{Float, Real};
 
 #[derive(Debug, Copy, Clone)]
-pub struct Quadrant {
-  x: f32,
-  y: f32,
-  length: f32,
+pub struct Quadrant<T: Float = Real> {
+  x: T,
+  y: T,
+  length: T,
 }
 
-impl Quadrant {
-  pub fn new(x: f32, y: f32, length: f32) -> Self {
+impl<T: Float> Quadrant<T> {
+  pub fn new(x: T, y: T, length: T) -> Self {
     Quadrant {
       x,
       y,
@@ -16,21 +17,21 @@ impl Quadrant {
     }
   }
 
-  pub fn get_x(&self) -> f32 {
+  pub fn get_x(&self) -> T {
     self.x
   }
 
-  pub fn get_y(&self) -> f32 {
+  pub fn get_y(&self) -> T {
     self.y
   }
 
-  pub fn get_length(&self) -> f32 {
+  pub fn get_length(&self) -> T {
     self.length
   }
 
   // Quadrant order: (ne, nw, se, sw)
-  pub fn get_child_quadrants(&self) -> (Quadrant, Quadrant, Quadrant, Quadrant) {
-    let child_length = self.length / 2 as f32;
+  pub fn get_child_quadrants(&self) -> (Quadrant<T>, Quadrant<T>, Quadrant<T>, Quadrant<T>) {
+    let child_length = self.length / T::from_f64(2.0);
     (
       Quadrant {
         x: self.x + child_length,
@@ -55,7 +56,7 @@ impl Quadrant {
     )
   }
 
-  pub fn contains(&self, coordinate: &Coordinate) -> bool {
+  pub fn contains(&self, coordinate: &Coordinate<T>) -> bool {
     let x = coordinate.get_x();
     let y = coordinate.get_y();
     let x_start = self.x;
@@ -67,6 +68,14 @@ impl Quadrant {
 
     x_contains && y_contains
   }
+
+  // Shortest distance from `coordinate` to the quadrant, zero inside it
+  pub fn get_distance_to(&self, coordinate: &Coordinate<T>) -> T {
+    let dx = (self.x - coordinate.get_x()).max(coordinate.get_x() - (self.x + self.length)).max(T::ZERO);
+    let dy = (self.y - coordinate.get_y()).max(coordinate.get_y() - (self.y + self.length)).max(T::ZERO);
+
+    (dx.powi(2) + dy.powi(2)).sqrt()
+  }
 }
 
 #[cfg(test)]
@@ -100,6 +109,15 @@ mod tests {
     assert_eq!(quadrant.contains(&coordinate), false);
   }
 
+  #[test]
+  fn test_get_distance_to() {
+    let quadrant = Quadrant::new(0.0, 0.0, 500.0);
+
+    assert_eq!(quadrant.get_distance_to(&Coordinate::new(200.0, 150.0)), 0.0);
+    assert_eq!(quadrant.get_distance_to(&Coordinate::new(600.0, 150.0)), 100.0);
+    assert_eq!(quadrant.get_distance_to(&Coordinate::new(530.0, -40.0)), 50.0);
+  }
+
   #[test]
   fn test_get_child_quadrants() {
     let quadrant = Quadrant::new(0.0, 500.0, 500.0);
@@ -0,0 +1,93 @@
+use std::ops::Add;
+
+use super::Vector;
+use crate::system::G;
+use crate::float::Real;
+
+// Which terms of a node's multipole expansion the tree walk uses, see `system::EXPANSION`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Expansion {
+  Monopole,
+  Quadrupole,
+}
+
+// Traceless quadrupole tensor about a node's centre of mass, Q_ij = sum m * (3 * d_i * d_j - |d|^2 * delta_ij),
+// where d is each body's offset from the centre. Only the in-plane components are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Quadrupole {
+  xx: Real,
+  xy: Real,
+  yy: Real,
+}
+
+impl Quadrupole {
+  // A point mass at offset (dx, dy) from the centre
+  pub fn new(mass: Real, dx: Real, dy: Real) -> Self {
+    Quadrupole {
+      xx: mass * (2.0 * dx.powi(2) - dy.powi(2)),
+      xy: mass * 3.0 * dx * dy,
+      yy: mass * (2.0 * dy.powi(2) - dx.powi(2)),
+    }
+  }
+
+  // In-plane trace, which for point masses is sum m * |d|^2
+  pub fn get_trace(&self) -> Real {
+    self.xx + self.yy
+  }
+
+  // The same mass distribution about a centre moved by (-dx, -dy), i.e. the old centre sits at
+  // offset (dx, dy) from the new one (parallel axis theorem)
+  pub fn shift(&self, mass: Real, dx: Real, dy: Real) -> Self {
+    *self + Quadrupole::new(mass, dx, dy)
+  }
+
+  // Quadrupole correction to the acceleration at offset (dx, dy) from the centre:
+  // G * (Q.r / r^5 - 5/2 * (r.Q.r) * r / r^7)
+  pub fn calculate_acceleration_at(&self, dx: Real, dy: Real) -> Vector {
+    let distance_squared = dx.powi(2) + dy.powi(2);
+    let (qx, qy) = (self.xx * dx + self.xy * dy, self.xy * dx + self.yy * dy);
+    let projection = dx * qx + dy * qy;
+    let inverse_fifth = distance_squared.powf(-2.5);
+
+    Vector::new(
+      qx - 2.5 * projection * dx / distance_squared,
+      qy - 2.5 * projection * dy / distance_squared,
+    ) * (G * inverse_fifth)
+  }
+
+  // Quadrupole correction to the potential at offset (dx, dy), per unit mass: -G/2 * (r.Q.r) / r^5
+  pub fn calculate_potential_at(&self, dx: Real, dy: Real) -> Real {
+    let projection = self.xx * dx.powi(2) + 2.0 * self.xy * dx * dy + self.yy * dy.powi(2);
+
+    -0.5 * G * projection * (dx.powi(2) + dy.powi(2)).powf(-2.5)
+  }
+}
+
+impl Add for Quadrupole {
+  type Output = Quadrupole;
+
+  fn add(self, rhs: Quadrupole) -> Quadrupole {
+    Quadrupole {
+      xx: self.xx + rhs.xx,
+      xy: self.xy + rhs.xy,
+      yy: self.yy + rhs.yy,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Quadrupole;
+
+  #[test]
+  fn test_shift() {
+    // Two unit masses at (-1, 0) and (1, 0) about their midpoint
+    let direct = Quadrupole::new(1.0, -1.0, 0.0) + Quadrupole::new(1.0, 1.0, 0.0);
+    // The mass at (1, 0) on its own, then shifted to the midpoint with the other mass added
+    let shifted = Quadrupole::default().shift(1.0, 1.0, 0.0) + Quadrupole::new(1.0, -1.0, 0.0);
+
+    assert_eq!(direct, shifted);
+    assert_eq!(direct, Quadrupole { xx: 4.0, xy: 0.0, yy: -2.0 });
+  }
+}
@@ -0,0 +1,125 @@
+use crate::body::{id::BodyId, Body};
+use crate::integrator::Field;
+use crate::quad_tree::{QuadTree, opening_criterion::OpeningCriterion};
+use crate::vector::Vector;
+use crate::float::Real;
+
+// Exact pairwise summation with the same kernel as the tree
+pub struct DirectSum;
+
+impl DirectSum {
+  pub fn calculate_net_force_on(bodies: &[Body], body: &Body) -> Vector {
+    bodies
+      .iter()
+      .filter(|other| other.get_id() != body.get_id())
+      .fold(Vector::new(0.0, 0.0), |acc, other| acc + other.calculate_force_on(body))
+  }
+}
+
+impl Field for DirectSum {
+  fn accelerations(&mut self, bodies: &[Body]) -> Vec<Vector> {
+    bodies
+      .iter()
+      .map(|body| DirectSum::calculate_net_force_on(bodies, body) / body.get_mass())
+      .collect()
+  }
+
+  fn accelerations_and_jerks_of(&mut self, bodies: &[Body], active: &[usize]) -> Vec<(Vector, Vector)> {
+    active
+      .iter()
+      .map(|&i| {
+        bodies
+          .iter()
+          .filter(|other| other.get_id() != bodies[i].get_id())
+          .fold((Vector::new(0.0, 0.0), Vector::new(0.0, 0.0)), |(acceleration, jerk), other| {
+            let (other_acceleration, other_jerk) = other.calculate_acceleration_and_jerk_on(&bodies[i]);
+            (acceleration + other_acceleration, jerk + other_jerk)
+          })
+      })
+      .collect()
+  }
+}
+
+// Relative error |F_tree - F_exact| / |F_exact| of the tree force on each of `bodies`
+pub fn compare_forces(quad_tree: &QuadTree, bodies: &[Body], criterion: OpeningCriterion) -> Vec<Real> {
+  bodies
+    .iter()
+    .map(|body| {
+      let exact = DirectSum::calculate_net_force_on(bodies, body);
+      let error = quad_tree.calculate_net_force_on(body, criterion) - exact;
+
+      error.get_norm() / exact.get_norm()
+    })
+    .collect()
+}
+
+// Resource with the latest tree versus direct sum comparison
+#[derive(Debug, Default)]
+pub struct ForceErrors {
+  per_body: Vec<(BodyId, Real)>,
+  mean: Real,
+  max: Real,
+  // 99th percentile
+  high: Real,
+}
+
+impl ForceErrors {
+  pub fn update(&mut self, bodies: &[Body], errors: Vec<Real>) {
+    let mut sorted: Vec<Real> = errors.iter().cloned().filter(|error| error.is_finite()).collect();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    self.mean = sorted.iter().sum::<Real>() / sorted.len().max(1) as Real;
+    self.max = sorted.last().cloned().unwrap_or(0.0);
+    self.high = sorted.get(sorted.len() * 99 / 100).cloned().unwrap_or(self.max);
+    self.per_body = bodies.iter().map(|body| *body.get_id()).zip(errors).collect();
+  }
+
+  pub fn get_per_body(&self) -> &Vec<(BodyId, Real)> {
+    &self.per_body
+  }
+
+  pub fn get_mean(&self) -> Real {
+    self.mean
+  }
+
+  pub fn get_max(&self) -> Real {
+    self.max
+  }
+
+  pub fn get_percentile_99(&self) -> Real {
+    self.high
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{compare_forces, ForceErrors};
+  use crate::test_support::get_spiral;
+  use crate::quad_tree::{QuadTree, opening_criterion::OpeningCriterion};
+
+  #[test]
+  fn test_tree_matches_direct_sum_when_fully_opened() {
+    let bodies = get_spiral(50);
+    let mut quad_tree = QuadTree::new(1000.0);
+    quad_tree.rebuild(&bodies);
+
+    let errors = compare_forces(&quad_tree, &bodies, OpeningCriterion::BarnesHut { theta: 0.0 });
+
+    assert!(errors.iter().all(|&error| error < 1e-4));
+  }
+
+  #[test]
+  fn test_force_errors() {
+    let bodies = get_spiral(50);
+    let mut quad_tree = QuadTree::new(1000.0);
+    quad_tree.rebuild(&bodies);
+    let mut force_errors = ForceErrors::default();
+
+    force_errors.update(&bodies, compare_forces(&quad_tree, &bodies, OpeningCriterion::BarnesHut { theta: 1.0 }));
+
+    assert_eq!(force_errors.get_per_body().len(), bodies.len());
+    assert!(force_errors.get_max() > 0.0);
+    assert!(force_errors.get_mean() <= force_errors.get_percentile_99());
+    assert!(force_errors.get_percentile_99() <= force_errors.get_max());
+  }
+}
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+
+use crate::body::{id::BodyId, Body};
+use crate::integrator::Field;
+use crate::quad_tree::{node::{ArenaNode, NodeArena}, opening_criterion::OpeningCriterion, QuadTree};
+use crate::system::G;
+use crate::vector::Vector;
+use crate::float::{to_f64, Real};
+
+// Coefficients of a bivariate expansion are stored by total degree, (0,0), (1,0), (0,1), (2,0), (1,1), ...
+fn get_index(i: usize, j: usize) -> usize {
+  let degree = i + j;
+  degree * (degree + 1) / 2 + j
+}
+
+fn get_count(order: usize) -> usize {
+  (order + 1) * (order + 2) / 2
+}
+
+fn get_exponents(order: usize) -> Vec<(usize, usize)> {
+  (0..=order).flat_map(|degree| (0..=degree).map(move |j| (degree - j, j))).collect()
+}
+
+// Taylor coefficients T_(i,j) = d^i/dx^i d^j/dy^j (1 / |r|) / (i! j!) at `(x, y)`, up to total degree `order`
+fn get_taylor_coefficients(x: f64, y: f64, order: usize) -> Vec<f64> {
+  let distance_squared = x.powi(2) + y.powi(2);
+  let mut coefficients = vec![0.0; get_count(order)];
+  coefficients[0] = 1.0 / distance_squared.sqrt();
+
+  // k |r|^2 T_a = -(2k - 1) sum_i r_i T_(a - e_i) - (k - 1) sum_i T_(a - 2 e_i), with k = |a|
+  for (i, j) in get_exponents(order).into_iter().skip(1) {
+    let k = (i + j) as f64;
+    let mut sum = 0.0;
+    if i >= 1 {
+      sum -= (2.0 * k - 1.0) * x * coefficients[get_index(i - 1, j)];
+    }
+    if j >= 1 {
+      sum -= (2.0 * k - 1.0) * y * coefficients[get_index(i, j - 1)];
+    }
+    if i >= 2 {
+      sum -= (k - 1.0) * coefficients[get_index(i - 2, j)];
+    }
+    if j >= 2 {
+      sum -= (k - 1.0) * coefficients[get_index(i, j - 2)];
+    }
+    coefficients[get_index(i, j)] = sum / (k * distance_squared);
+  }
+
+  coefficients
+}
+
+#[derive(Debug)]
+struct Cell {
+  centre: (f64, f64),
+  // Half diagonal of the quadrant, bounds the distance from `centre` to any body in the cell
+  radius: f64,
+  children: Vec<usize>,
+  // Only leaves hold bodies, their tree node's bucket
+  bodies: Vec<Body>,
+  // M_a = sum m * d^a about `centre`
+  multipole: Vec<f64>,
+  // Phi(centre + e) = sum L_a * e^a for the far field, where the potential is -G * Phi
+  local: Vec<f64>,
+  // Near field acceleration on each of `bodies`, summed directly
+  near: Vec<Vector>,
+}
+
+// Fast multipole method on the quad tree's cells: multipoles are built bottom-up, translated to local
+// expansions between well separated cells (M2L) in a dual tree walk and pushed down to the bodies.
+// The far field uses the unsoftened kernel, neighbouring leaves are summed directly with softening.
+#[derive(Debug)]
+pub struct FastMultipole {
+  order: usize,
+  // Cells interact through their expansions when (r_a + r_b) / distance < theta
+  theta: Real,
+  binomials: Vec<Vec<f64>>,
+  cells: Vec<Cell>,
+  accelerations: HashMap<BodyId, Vector>,
+}
+
+impl FastMultipole {
+  pub fn new(order: usize, theta: Real) -> Self {
+    let mut binomials = vec![vec![1.0; order + 1]; order + 1];
+    for n in 1..=order {
+      for k in 1..n {
+        binomials[n][k] = binomials[n - 1][k - 1] + binomials[n - 1][k];
+      }
+    }
+
+    FastMultipole {
+      order,
+      theta,
+      binomials,
+      cells: Vec::new(),
+      accelerations: HashMap::new(),
+    }
+  }
+
+  // Computes the acceleration of every body in `quad_tree`
+  pub fn evaluate(&mut self, quad_tree: &QuadTree) {
+    self.cells.clear();
+    self.accelerations.clear();
+    if quad_tree.get_root().get_body().is_none() {
+      return;
+    }
+
+    self.add_cell(quad_tree.get_nodes(), quad_tree.get_nodes().get_root());
+    self.interact(0, 0);
+    self.push_down(0);
+  }
+
+  pub fn calculate_net_force_on(&self, body: &Body) -> Vector {
+    match self.accelerations.get(body.get_id()) {
+      Some(acceleration) => *acceleration * body.get_mass(),
+      None => Vector::new(0.0, 0.0),
+    }
+  }
+
+  // P2M and M2M, returns the index of the new cell
+  fn add_cell(&mut self, nodes: &NodeArena, node: &ArenaNode) -> usize {
+    let quadrant = node.get_quadrant();
+    let half_length = to_f64(quadrant.get_length()) / 2.0;
+    let centre = (to_f64(quadrant.get_x()) + half_length, to_f64(quadrant.get_y()) + half_length);
+    let index = self.cells.len();
+    self.cells.push(Cell {
+      centre,
+      radius: half_length * 2.0_f64.sqrt(),
+      children: Vec::new(),
+      bodies: Vec::new(),
+      multipole: vec![0.0; get_count(self.order)],
+      local: vec![0.0; get_count(self.order)],
+      near: Vec::new(),
+    });
+
+    match node.get_children() {
+      Some(children) => {
+        let mut multipole = vec![0.0; get_count(self.order)];
+        for &child in &[children.ne, children.nw, children.se, children.sw] {
+          let child = nodes.get(child);
+          if child.get_body().is_none() {
+            continue;
+          }
+          let child_index = self.add_cell(nodes, child);
+          self.cells[index].children.push(child_index);
+
+          let child_cell = &self.cells[child_index];
+          let (dx, dy) = (child_cell.centre.0 - centre.0, child_cell.centre.1 - centre.1);
+          for (i, j) in get_exponents(self.order) {
+            for (k, l) in get_exponents(i + j) {
+              if k > i || l > j {
+                continue;
+              }
+              multipole[get_index(i, j)] += self.binomials[i][k] * self.binomials[j][l]
+                * child_cell.multipole[get_index(k, l)]
+                * dx.powi((i - k) as i32)
+                * dy.powi((j - l) as i32);
+            }
+          }
+        }
+        self.cells[index].multipole = multipole;
+      }
+      None => {
+        let cell = &mut self.cells[index];
+        for body in node.get_bucket() {
+          let dx = to_f64(body.get_coordinate().get_x()) - centre.0;
+          let dy = to_f64(body.get_coordinate().get_y()) - centre.1;
+          let mass = to_f64(body.get_mass());
+          for (i, j) in get_exponents(self.order) {
+            cell.multipole[get_index(i, j)] += mass * dx.powi(i as i32) * dy.powi(j as i32);
+          }
+        }
+        cell.bodies = node.get_bucket().to_vec();
+        cell.near = vec![Vector::new(0.0, 0.0); cell.bodies.len()];
+      }
+    }
+
+    index
+  }
+
+  // Dual tree walk accumulating the field of `source` onto `target`
+  fn interact(&mut self, target: usize, source: usize) {
+    if target == source {
+      let children = self.cells[target].children.clone();
+      if children.is_empty() {
+        self.interact_directly(target, source);
+      }
+      for &child_target in &children {
+        for &child_source in &children {
+          self.interact(child_target, child_source);
+        }
+      }
+      return;
+    }
+
+    let (target_cell, source_cell) = (&self.cells[target], &self.cells[source]);
+    let (dx, dy) = (target_cell.centre.0 - source_cell.centre.0, target_cell.centre.1 - source_cell.centre.1);
+    let distance = (dx.powi(2) + dy.powi(2)).sqrt();
+
+    if target_cell.radius + source_cell.radius < to_f64(self.theta) * distance {
+      self.translate_multipole_to_local(target, source, dx, dy);
+    } else if target_cell.children.is_empty() && source_cell.children.is_empty() {
+      self.interact_directly(target, source);
+    } else if source_cell.children.is_empty()
+      || (!target_cell.children.is_empty() && target_cell.radius >= source_cell.radius)
+    {
+      for child in target_cell.children.clone() {
+        self.interact(child, source);
+      }
+    } else {
+      for child in source_cell.children.clone() {
+        self.interact(target, child);
+      }
+    }
+  }
+
+  // P2P between two leaf buckets, or within one
+  fn interact_directly(&mut self, target: usize, source: usize) {
+    let sources = self.cells[source].bodies.clone();
+    let cell = &mut self.cells[target];
+    for (body, near) in cell.bodies.iter().zip(cell.near.iter_mut()) {
+      for other in sources.iter().filter(|other| other.get_id() != body.get_id()) {
+        let (acceleration, _) = other.calculate_acceleration_and_jerk_on(body);
+        *near = *near + acceleration;
+      }
+    }
+  }
+
+  // M2L: L_b += sum_a (-1)^|a| C(a + b, a) M_a T_(a + b)(r), truncated at |a| + |b| <= order
+  fn translate_multipole_to_local(&mut self, target: usize, source: usize, dx: f64, dy: f64) {
+    let coefficients = get_taylor_coefficients(dx, dy, self.order);
+    let exponents = get_exponents(self.order);
+    let multipole = &self.cells[source].multipole;
+    let mut local = self.cells[target].local.clone();
+
+    for &(k, l) in &exponents {
+      for &(i, j) in &exponents {
+        if i + j + k + l > self.order {
+          break;
+        }
+        let sign = if (i + j) % 2 == 0 { 1.0 } else { -1.0 };
+        local[get_index(k, l)] += sign * self.binomials[i + k][i] * self.binomials[j + l][j]
+          * multipole[get_index(i, j)]
+          * coefficients[get_index(i + k, j + l)];
+      }
+    }
+
+    self.cells[target].local = local;
+  }
+
+  // L2L into the children, L2P at the leaves
+  fn push_down(&mut self, index: usize) {
+    let exponents = get_exponents(self.order);
+    let local = self.cells[index].local.clone();
+    let centre = self.cells[index].centre;
+
+    for child in self.cells[index].children.clone() {
+      let (dx, dy) = (self.cells[child].centre.0 - centre.0, self.cells[child].centre.1 - centre.1);
+      let child_local = &mut self.cells[child].local;
+      for &(k, l) in &exponents {
+        for &(i, j) in &exponents {
+          if i < k || j < l {
+            continue;
+          }
+          child_local[get_index(k, l)] += self.binomials[i][k] * self.binomials[j][l]
+            * local[get_index(i, j)]
+            * dx.powi((i - k) as i32)
+            * dy.powi((j - l) as i32);
+        }
+      }
+      self.push_down(child);
+    }
+
+    let cell = &self.cells[index];
+    for (body, near) in cell.bodies.iter().zip(&cell.near) {
+      let dx = to_f64(body.get_coordinate().get_x()) - centre.0;
+      let dy = to_f64(body.get_coordinate().get_y()) - centre.1;
+      let (mut gradient_x, mut gradient_y) = (0.0, 0.0);
+      for &(i, j) in &exponents {
+        if i >= 1 {
+          gradient_x += i as f64 * local[get_index(i, j)] * dx.powi(i as i32 - 1) * dy.powi(j as i32);
+        }
+        if j >= 1 {
+          gradient_y += j as f64 * local[get_index(i, j)] * dx.powi(i as i32) * dy.powi(j as i32 - 1);
+        }
+      }
+      let far = Vector::new((to_f64(G) * gradient_x) as Real, (to_f64(G) * gradient_y) as Real);
+
+      let previous = self.accelerations.insert(*body.get_id(), far + *near);
+      debug_assert!(previous.is_none(), "bodies share an id");
+    }
+  }
+}
+
+// Evaluates accelerations with the fast multipole method, rebuilding the tree like `QuadTreeField`.
+// Jerks aren't expanded, so schemes that need them fall back to the tree walk.
+pub struct FastMultipoleField<'a> {
+  quad_tree: &'a mut QuadTree,
+  fast_multipole: FastMultipole,
+  criterion: OpeningCriterion,
+  is_current: bool,
+}
+
+impl<'a> FastMultipoleField<'a> {
+  pub fn new(quad_tree: &'a mut QuadTree, order: usize, theta: Real, criterion: OpeningCriterion) -> Self {
+    FastMultipoleField {
+      quad_tree,
+      fast_multipole: FastMultipole::new(order, theta),
+      criterion,
+      is_current: true,
+    }
+  }
+
+  fn prepare(&mut self, bodies: &[Body]) {
+    // Built for this step by `BodyQuadTreeSystem`, so later evaluations only refit it
+    if !self.is_current {
+      self.quad_tree.refit(bodies);
+    }
+    self.is_current = false;
+  }
+}
+
+impl<'a> Field for FastMultipoleField<'a> {
+  fn accelerations(&mut self, bodies: &[Body]) -> Vec<Vector> {
+    self.prepare(bodies);
+    self.fast_multipole.evaluate(self.quad_tree);
+
+    bodies
+      .iter()
+      .map(|body| self.fast_multipole.calculate_net_force_on(body) / body.get_mass())
+      .collect()
+  }
+
+  fn accelerations_and_jerks_of(&mut self, bodies: &[Body], active: &[usize]) -> Vec<(Vector, Vector)> {
+    self.prepare(bodies);
+
+    active
+      .iter()
+      .map(|&i| self.quad_tree.calculate_net_acceleration_and_jerk_on(&bodies[i], self.criterion))
+      .collect()
+  }
+
+  fn invalidate(&mut self) {
+    self.is_current = false;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{get_taylor_coefficients, FastMultipole, Real};
+  use crate::test_support::get_spiral;
+  use crate::quad_tree::QuadTree;
+  use crate::vector::Vector;
+
+  fn get_mean_error(order: usize) -> Real {
+    let bodies = get_spiral(200);
+    let mut quad_tree = QuadTree::new(1000.0);
+    quad_tree.rebuild(&bodies);
+    let mut fast_multipole = FastMultipole::new(order, 0.5);
+    fast_multipole.evaluate(&quad_tree);
+
+    bodies
+      .iter()
+      .map(|body| {
+        let exact = bodies
+          .iter()
+          .filter(|other| other.get_id() != body.get_id())
+          .fold(Vector::new(0.0, 0.0), |acc, other| acc + other.calculate_acceleration_and_jerk_on(body).0);
+        (fast_multipole.calculate_net_force_on(body) / body.get_mass() - exact).get_norm() / exact.get_norm()
+      })
+      .sum::<Real>()
+      / bodies.len() as Real
+  }
+
+  #[test]
+  fn test_taylor_coefficients() {
+    let coefficients = get_taylor_coefficients(3.0, 4.0, 2);
+
+    assert!((coefficients[0] - 0.2).abs() < 1e-12);
+    assert!((coefficients[1] + 3.0 / 125.0).abs() < 1e-12);
+    assert!((coefficients[2] + 4.0 / 125.0).abs() < 1e-12);
+    // (3x^2 - r^2) / (2 r^5)
+    assert!((coefficients[3] - 2.0 / 6250.0).abs() < 1e-12);
+    // 3xy / r^5
+    assert!((coefficients[4] - 36.0 / 3125.0).abs() < 1e-12);
+  }
+
+  #[test]
+  fn test_error_falls_with_order() {
+    let (low, high) = (get_mean_error(2), get_mean_error(6));
+
+    assert!(high < 1e-3);
+    assert!(high < low);
+  }
+}
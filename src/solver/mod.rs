@@ -0,0 +1,77 @@
+mod direct_sum;
+mod fast_multipole;
+
+pub use self::direct_sum::{compare_forces, DirectSum, ForceErrors};
+pub use self::fast_multipole::FastMultipoleField;
+
+use crate::body::Body;
+use crate::integrator::Field;
+use crate::parallel::Walker;
+use crate::quad_tree::{QuadTree, opening_criterion::OpeningCriterion};
+use crate::vector::Vector;
+use crate::float::Real;
+
+// How forces are evaluated, see `system::SOLVER`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Solver {
+  // O(N log N) tree walk, opening nodes by `OPENING_CRITERION`
+  BarnesHut,
+  // Exact O(N^2) pairwise sum, the reference for the tree
+  Direct,
+  // O(N) fast multipole method on the tree's cells, with expansions truncated at `order`
+  FastMultipole { order: usize, theta: Real },
+}
+
+// Evaluates accelerations with the Barnes-Hut tree. The first evaluation of a step uses the
+// tree `BodyQuadTreeSystem` built for the current state, later ones update it for the moved bodies.
+// The walks for each body are shared out by `walker`.
+pub struct QuadTreeField<'a> {
+  quad_tree: &'a mut QuadTree,
+  criterion: OpeningCriterion,
+  walker: &'a Walker,
+  is_current: bool,
+}
+
+impl<'a> QuadTreeField<'a> {
+  pub fn new(quad_tree: &'a mut QuadTree, criterion: OpeningCriterion, walker: &'a Walker) -> Self {
+    QuadTreeField {
+      quad_tree,
+      criterion,
+      walker,
+      is_current: true,
+    }
+  }
+
+  fn prepare(&mut self, bodies: &[Body]) {
+    // Built for this step by `BodyQuadTreeSystem`, so later evaluations only refit it
+    if !self.is_current {
+      self.quad_tree.refit(bodies);
+    }
+    self.is_current = false;
+  }
+}
+
+impl<'a> Field for QuadTreeField<'a> {
+  fn accelerations(&mut self, bodies: &[Body]) -> Vec<Vector> {
+    self.prepare(bodies);
+    let (quad_tree, criterion) = (&*self.quad_tree, self.criterion);
+
+    self.walker.map(bodies, |body| {
+      let force = quad_tree.calculate_net_force_on(body, criterion);
+      let mass = body.get_mass();
+      force / mass
+    })
+  }
+
+  fn accelerations_and_jerks_of(&mut self, bodies: &[Body], active: &[usize]) -> Vec<(Vector, Vector)> {
+    self.prepare(bodies);
+    let (quad_tree, criterion) = (&*self.quad_tree, self.criterion);
+
+    self.walker.map(active, |&i| quad_tree.calculate_net_acceleration_and_jerk_on(&bodies[i], criterion))
+  }
+
+  fn invalidate(&mut self) {
+    self.is_current = false;
+  }
+}
@@ -1,44 +1,200 @@
 use amethyst::{
   assets::{AssetStorage, Loader, Handle},
-  core::{transform::Transform, math::Vector3},
+  core::{timing::Time, transform::Transform, math::Vector3},
+  ecs::prelude::{Dispatcher, DispatcherBuilder, Join},
   prelude::*,
   renderer::{Camera, ImageFormat, SpriteRender, SpriteSheet, SpriteSheetFormat, Texture},
 };
 
 use rand::{thread_rng, Rng};
-use crate::body::{Body, coordinate::Coordinate};
-use crate::quad_tree::QuadTree;
+use crate::body::{Body, coordinate::Coordinate, selection::Selection, softening::{Kernel, Softening}};
+use crate::boundary::BoundaryPolicy;
+use crate::camera::OrbitCamera;
+use crate::collision::CollisionResponse;
+use crate::diagnostics::Diagnostics;
+use crate::integrator::IntegratorKind;
+use crate::oct_tree::{Dimensions, OctTree};
+use crate::quad_tree::{QuadTree, TreeBuild, bounds::RootBounds, node::{LeafLimits, DEPTH_LIMIT}, opening_criterion::OpeningCriterion, quadrant::Quadrant, quadrupole::Expansion};
+use crate::solver::Solver;
+use crate::systems;
+use crate::timestep::FixedTimestep;
+use crate::float::{to_f32, Real};
 
+// 3D runs use the oct tree and skip collisions, diagnostics and force comparison, which are 2D only
+pub const DIMENSIONS: Dimensions = Dimensions::Two;
 pub const NUM_BODIES: u32 = 1000;
-pub const MAX_MASS: f32 = 100.0;
-pub const ARENA_LENGTH: f32 = 1000.0;
-pub const PADDING: f32 = 10.0;
+pub const MAX_MASS: Real = 100.0;
+pub const ARENA_LENGTH: Real = 1000.0;
+pub const PADDING: Real = 10.0;
+// The particle sprite is 5 pixels across at a scale of 1
+pub const SPRITE_RADIUS: Real = 2.5;
 // Actual value of G is 6.67e-11f64 but it's been adjusted to suit the system
-pub const G: f32 = 6.67e-3f32;
+pub const G: Real = 6.67e-3;
+// Keeps close encounters finite, applied to every body-body and node-body interaction
+pub const SOFTENING: Softening = Softening::new(Kernel::Plummer, 1.0);
+pub const INTEGRATOR: IntegratorKind = IntegratorKind::Leapfrog;
+pub const SOLVER: Solver = Solver::BarnesHut;
+pub const EXPANSION: Expansion = Expansion::Quadrupole;
+pub const OPENING_CRITERION: OpeningCriterion = OpeningCriterion::BarnesHut { theta: 0.5 };
+// Joins opposite edges of the arena, with an Ewald correction for the periodic images. Only the
+// Barnes-Hut solver in 2D sees the images, so startup rejects any other solver, 3D, collisions,
+// diagnostics and force comparisons along with it.
+pub const PERIODIC: bool = false;
+// How the quad or oct tree root is refitted on every rebuild, so that bodies leaving the arena still pull
+// and are pulled. Periodic runs keep the arena as the root.
+pub const ROOT_BOUNDS: RootBounds = RootBounds::BoundingBox;
+// Quad and oct tree leaves hold up to LEAF_CAPACITY bodies, summed directly, and stop splitting at MAX_DEPTH,
+// which is no deeper than `Real` can resolve
+pub const LEAF_CAPACITY: usize = 8;
+pub const MAX_DEPTH: usize = DEPTH_LIMIT;
+// Large trees are built as 16 subtrees in parallel and merged
+pub const TREE_BUILD: TreeBuild = TreeBuild::Parallel { levels: 2, min_bodies: 512 };
+// When set, the quad tree is only rebuilt every REBUILD_INTERVAL physics steps, and refitted on the rest.
+// In between it keeps its nodes, moves the bodies that changed leaf and recomputes the aggregates.
+// Evaluations within a step always refit.
+pub const REBUILD_INTERVAL: Option<usize> = None;
+// Threads sharing the per-body tree walks, 0 for one per core and 1 to walk on the physics thread.
+// The forces come out the same whatever the count.
+pub const FORCE_THREADS: usize = 0;
+// Physics runs in fixed steps of TIME_STEP seconds, at most MAX_SUBSTEPS of them per rendered frame
+pub const TIME_STEP: Real = 1.0 / 60.0;
+pub const MAX_SUBSTEPS: u32 = 5;
+// None lets bodies pass through each other
+pub const COLLISION_RESPONSE: Option<CollisionResponse> = Some(CollisionResponse::Merge);
+// What happens to bodies that leave the arena after a step. None lets them go, with the quad tree root
+// following them as ROOT_BOUNDS asks.
+// Only the 2D dispatcher runs the boundary system; the policies act in the plane, so with
+// Dimensions::Three bodies leave the arena whatever this is set to.
+pub const BOUNDARY_POLICY: Option<BoundaryPolicy> = None;
+// When set, energy, momentum and angular momentum are sampled every so many physics steps
+pub const DIAGNOSTICS_INTERVAL: Option<u64> = Some(60);
+// When set, the tree forces are compared against the direct sum every so many physics steps
+pub const FORCE_COMPARISON_INTERVAL: Option<u64> = None;
+// 3D camera controls, in radians per second and e-folds of distance per second
+pub const ORBIT_SPEED: Real = 1.0;
+pub const ZOOM_SPEED: Real = 1.0;
+// Keeps the centre of mass in the middle of the screen
+pub const CAMERA_FOLLOW: bool = false;
+// Selects the heaviest body at the start, for CAMERA_FOLLOW to follow it instead. The selection passes
+// on to whichever body absorbs it in a merge.
+pub const SELECT_HEAVIEST: bool = false;
 
-pub struct SystemState;
+// Physics systems live in their own dispatcher, run once per fixed step
+#[derive(Default)]
+pub struct SystemState<'a, 'b> {
+  dispatcher: Option<Dispatcher<'a, 'b>>,
+}
 
-impl SimpleState for SystemState {
+impl<'a, 'b> SimpleState for SystemState<'a, 'b> {
   fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
+    if PERIODIC {
+      check_periodic();
+    }
+
     let world = data.world;
     world.register::<Body>();
     world.register::<QuadTree>();
+    world.register::<OctTree>();
+    world.insert(FixedTimestep::new(TIME_STEP, MAX_SUBSTEPS));
+    if let Some(interval) = DIAGNOSTICS_INTERVAL {
+      world.insert(Diagnostics::new(interval));
+    }
+
+    let mut dispatcher = match DIMENSIONS {
+      Dimensions::Two => build_dispatcher_2d(),
+      Dimensions::Three { .. } => {
+        world.insert(OrbitCamera::new(Coordinate::new(ARENA_LENGTH * 0.5, ARENA_LENGTH * 0.5), ARENA_LENGTH));
+        DispatcherBuilder::new()
+          .with(systems::BodyOctTreeSystem, "body_oct_tree_system", &[])
+          .with(systems::BodyForceSystem::new(INTEGRATOR, SOLVER, DIMENSIONS), "body_force_system", &["body_oct_tree_system"])
+          .build()
+      }
+    };
+    dispatcher.setup(world);
+    self.dispatcher = Some(dispatcher);
 
     initialise_camera(world);
-    initialise_quad_tree(world);
+    match DIMENSIONS {
+      Dimensions::Two => initialise_quad_tree(world),
+      Dimensions::Three { .. } => initialise_oct_tree(world),
+    }
     let sprite_sheet_handle = load_sprite_sheet(world);
     initialise_bodies(world, sprite_sheet_handle.clone());
+    initialise_selection(world);
+  }
+
+  fn update(&mut self, data: &mut StateData<'_, GameData<'_, '_>>) -> SimpleTrans {
+    let elapsed = data.world.read_resource::<Time>().delta_seconds();
+    let steps = data.world.write_resource::<FixedTimestep>().advance(elapsed);
+
+    if let Some(dispatcher) = self.dispatcher.as_mut() {
+      for _ in 0..steps {
+        dispatcher.dispatch(data.world);
+        data.world.maintain();
+      }
+    }
+
+    Trans::None
+  }
+}
+
+// Everything but the Barnes-Hut walk treats the arena as open, see `PERIODIC`
+fn check_periodic() {
+  assert!(SOLVER == Solver::BarnesHut, "a periodic arena needs Solver::BarnesHut");
+  assert!(DIMENSIONS == Dimensions::Two, "a periodic arena needs Dimensions::Two");
+  assert!(COLLISION_RESPONSE.is_none(), "collisions don't see the periodic images, set COLLISION_RESPONSE to None");
+  assert!(DIAGNOSTICS_INTERVAL.is_none(), "the potential energy misses the periodic images, set DIAGNOSTICS_INTERVAL to None");
+  assert!(FORCE_COMPARISON_INTERVAL.is_none(), "the direct sum misses the periodic images, set FORCE_COMPARISON_INTERVAL to None");
+}
+
+// Collisions, diagnostics and force comparison all rely on the quad tree
+fn build_dispatcher_2d<'a, 'b>() -> Dispatcher<'a, 'b> {
+  let dispatcher_builder = DispatcherBuilder::new()
+    .with(systems::BodyQuadTreeSystem::new(), "body_quad_tree_system", &[])
+    .with(systems::BodyForceSystem::new(INTEGRATOR, SOLVER, DIMENSIONS), "body_force_system", &["body_quad_tree_system"]);
+  let dispatcher_builder = match COLLISION_RESPONSE {
+    Some(CollisionResponse::Merge) => dispatcher_builder
+      .with(systems::BodyMergeSystem, "body_collision_system", &["body_force_system"]),
+    Some(CollisionResponse::Bounce { restitution }) => dispatcher_builder
+      .with(systems::BodyBounceSystem::new(restitution), "body_collision_system", &["body_force_system"]),
+    None => dispatcher_builder,
+  };
+  let last_system = match COLLISION_RESPONSE {
+    Some(_) => "body_collision_system",
+    None => "body_force_system",
+  };
+  // Runs after collisions, which would otherwise still see the bodies it deletes
+  let dispatcher_builder = match BOUNDARY_POLICY {
+    Some(policy) => {
+      let arena = Quadrant::new(0.0, 0.0, ARENA_LENGTH - PADDING);
+      dispatcher_builder
+        .with(systems::BodyBoundarySystem::new(policy, arena), "body_boundary_system", &[last_system])
+    }
+    None => dispatcher_builder,
+  };
+  let last_system = match BOUNDARY_POLICY {
+    Some(_) => "body_boundary_system",
+    None => last_system,
+  };
+  let mut dispatcher_builder = dispatcher_builder;
+  if DIAGNOSTICS_INTERVAL.is_some() {
+    dispatcher_builder.add(systems::DiagnosticsSystem, "diagnostics_system", &[last_system]);
+  }
+  if let Some(interval) = FORCE_COMPARISON_INTERVAL {
+    dispatcher_builder.add(systems::ForceComparisonSystem::new(interval), "force_comparison_system", &[last_system]);
   }
+
+  dispatcher_builder.build()
 }
 
 fn initialise_camera(world: &mut World) {
   // Setup camera in a way that our screen covers whole arena and (0, 0) is in the bottom left.
   let mut transform = Transform::default();
-  transform.set_translation_xyz(ARENA_LENGTH * 0.5, ARENA_LENGTH * 0.5, 1.0);
+  transform.set_translation_xyz(to_f32(ARENA_LENGTH) * 0.5, to_f32(ARENA_LENGTH) * 0.5, 1.0);
 
   world
       .create_entity()
-      .with(Camera::standard_2d(ARENA_LENGTH, ARENA_LENGTH))
+      .with(Camera::standard_2d(to_f32(ARENA_LENGTH), to_f32(ARENA_LENGTH)))
       .with(transform)
       .build();
 }
@@ -77,16 +233,20 @@ fn initialise_bodies(world: &mut World, sprite_sheet: Handle<SpriteSheet>) {
   let mut rng = thread_rng();
 
   (1..=NUM_BODIES).into_iter().for_each(|_| {
-    let x = rng.gen_range((ARENA_LENGTH / 2.0) - 10.0, (ARENA_LENGTH / 2.0) + 10.0) as f32;
-    let y = rng.gen_range((ARENA_LENGTH / 2.0) - 10.0, (ARENA_LENGTH / 2.0) + 10.0) as f32;
-    let mass = rng.gen_range(1.0, MAX_MASS) as f32;
+    let x = rng.gen_range((ARENA_LENGTH / 2.0) - 10.0, (ARENA_LENGTH / 2.0) + 10.0) as Real;
+    let y = rng.gen_range((ARENA_LENGTH / 2.0) - 10.0, (ARENA_LENGTH / 2.0) + 10.0) as Real;
+    let z = match DIMENSIONS {
+      Dimensions::Two => 0.0,
+      Dimensions::Three { .. } => rng.gen_range((ARENA_LENGTH / 2.0) - 10.0, (ARENA_LENGTH / 2.0) + 10.0) as Real,
+    };
+    let mass = rng.gen_range(1.0, MAX_MASS) as Real;
 
     let mut local_tranform = Transform::default();
-    local_tranform.set_translation_xyz(x, y, 0.0);
-    let scale_factor = mass / (MAX_MASS * 0.5);
-    local_tranform.set_scale(Vector3::new(scale_factor, scale_factor, 0.0));
+    local_tranform.set_translation_xyz(to_f32(x), to_f32(y), 0.0);
+    let scale_factor = get_scale_factor(mass);
+    local_tranform.set_scale(Vector3::new(to_f32(scale_factor), to_f32(scale_factor), 0.0));
 
-    let body = Body::new(mass, Coordinate::new(x, y ));
+    let body = Body::new_with_radius(mass, Coordinate::new_3d(x, y, z), SPRITE_RADIUS * scale_factor);
 
     world
       .create_entity()
@@ -97,11 +257,48 @@ fn initialise_bodies(world: &mut World, sprite_sheet: Handle<SpriteSheet>) {
   });
 }
 
+fn initialise_selection(world: &mut World) {
+  let mut selection = Selection::default();
+  if SELECT_HEAVIEST {
+    let bodies = world.read_storage::<Body>();
+    let heaviest = (&bodies).join().fold(None, |heaviest: Option<&Body>, body| match heaviest {
+      Some(heaviest) if heaviest.get_mass() >= body.get_mass() => Some(heaviest),
+      _ => Some(body),
+    });
+    if let Some(body) = heaviest {
+      selection.select(*body.get_id());
+    }
+  }
+  world.insert(selection);
+}
+
+// Sprites, and so bodies, grow with their mass
+pub fn get_scale_factor(mass: Real) -> Real {
+  mass / (MAX_MASS * 0.5)
+}
+
 fn initialise_quad_tree(world: &mut World) {
-  let quad_tree = QuadTree::new(ARENA_LENGTH - PADDING);
+  let mut quad_tree = QuadTree::new(ARENA_LENGTH - PADDING);
+  quad_tree.set_expansion(EXPANSION);
+  quad_tree.set_root_bounds(ROOT_BOUNDS);
+  quad_tree.set_leaf_limits(LeafLimits { capacity: LEAF_CAPACITY, max_depth: MAX_DEPTH });
+  quad_tree.set_build(TREE_BUILD);
+  quad_tree.set_rebuild_interval(REBUILD_INTERVAL);
+  quad_tree.set_periodic(PERIODIC);
 
   world
     .create_entity()
     .with(quad_tree)
     .build();
-}
\ No newline at end of file
+}
+
+fn initialise_oct_tree(world: &mut World) {
+  let mut oct_tree = OctTree::new(ARENA_LENGTH - PADDING);
+  oct_tree.set_root_bounds(ROOT_BOUNDS);
+  oct_tree.set_leaf_limits(LeafLimits { capacity: LEAF_CAPACITY, max_depth: MAX_DEPTH });
+
+  world
+    .create_entity()
+    .with(oct_tree)
+    .build();
+}
@@ -0,0 +1,49 @@
+use amethyst::{
+  core::SystemDesc,
+  derive::SystemDesc,
+  ecs::prelude::{Join, ReadStorage, System, SystemData, World, WriteStorage},
+};
+
+use crate::body::Body;
+use crate::collision::{find_overlapping_pairs, resolve_bounce};
+use crate::quad_tree::QuadTree;
+use crate::float::Real;
+
+// Bounces overlapping bodies off each other with a coefficient of restitution
+#[derive(SystemDesc)]
+pub struct BodyBounceSystem {
+  restitution: Real,
+}
+
+impl BodyBounceSystem {
+  pub fn new(restitution: Real) -> Self {
+    BodyBounceSystem { restitution }
+  }
+}
+
+impl<'s> System<'s> for BodyBounceSystem {
+  type SystemData = (
+    ReadStorage<'s, QuadTree>,
+    WriteStorage<'s, Body>,
+  );
+
+  fn run(&mut self, (quad_trees, mut bodies): Self::SystemData) {
+    for quad_tree in (&quad_trees).join() {
+      let mut snapshot: Vec<Body> = (&bodies).join().cloned().collect();
+      let pairs = find_overlapping_pairs(quad_tree, &snapshot);
+      if pairs.is_empty() {
+        continue;
+      }
+
+      for (i, j) in pairs {
+        let (head, tail) = snapshot.split_at_mut(j);
+        resolve_bounce(&mut head[i], &mut tail[0], self.restitution);
+      }
+
+      for (body, next) in (&mut bodies).join().zip(snapshot) {
+        debug_assert_eq!(body.get_id(), next.get_id(), "snapshot is out of order");
+        *body = next;
+      }
+    }
+  }
+}
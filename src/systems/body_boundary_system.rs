@@ -0,0 +1,64 @@
+use amethyst::{
+  core::{transform::Transform, SystemDesc},
+  derive::SystemDesc,
+  ecs::prelude::{Entities, Join, System, SystemData, World, Write, WriteStorage},
+};
+use log::info;
+
+use crate::body::Body;
+use crate::boundary::{BoundaryCounts, BoundaryPolicy, BoundaryReport};
+use crate::quad_tree::quadrant::Quadrant;
+use crate::float::to_f32;
+
+// Applies the boundary policy to the bodies that left `quadrant` in the last step and records how many
+// there were in the `BoundaryReport`
+#[derive(SystemDesc)]
+pub struct BodyBoundarySystem {
+  policy: BoundaryPolicy,
+  quadrant: Quadrant,
+}
+
+impl BodyBoundarySystem {
+  pub fn new(policy: BoundaryPolicy, quadrant: Quadrant) -> Self {
+    BodyBoundarySystem { policy, quadrant }
+  }
+}
+
+impl<'s> System<'s> for BodyBoundarySystem {
+  type SystemData = (
+    Entities<'s>,
+    WriteStorage<'s, Body>,
+    WriteStorage<'s, Transform>,
+    Write<'s, BoundaryReport>,
+  );
+
+  fn run(&mut self, (entities, mut bodies, mut transforms, mut report): Self::SystemData) {
+    let mut counts = BoundaryCounts::default();
+
+    for (entity, body, transform) in (&entities, &mut bodies, (&mut transforms).maybe()).join() {
+      if !self.policy.apply(&self.quadrant, body) {
+        continue;
+      }
+      counts.add(self.policy);
+
+      if self.policy == BoundaryPolicy::Remove {
+        entities.delete(entity).expect("body outside the arena was already deleted");
+      } else if let Some(transform) = transform {
+        // Until the next frame interpolates it, the sprite shouldn't linger outside the arena
+        transform.set_translation_x(to_f32(body.get_coordinate().get_x()));
+        transform.set_translation_y(to_f32(body.get_coordinate().get_y()));
+      }
+    }
+
+    if counts.get_total() > 0 {
+      info!(
+        "boundary: removed {}, reflected {}, wrapped {}, clamped {}",
+        counts.removed,
+        counts.reflected,
+        counts.wrapped,
+        counts.clamped,
+      );
+    }
+    report.record(counts);
+  }
+}
@@ -1,40 +1,98 @@
 use amethyst::{
-  core::{timing::Time, transform::Transform, SystemDesc},
+  core::SystemDesc,
   derive::SystemDesc,
-  ecs::prelude::{Join, Read, ReadStorage, System, SystemData, World, WriteStorage},
+  ecs::prelude::{Join, ReadExpect, System, SystemData, World, WriteStorage},
 };
 
 use crate::body::Body;
+use crate::integrator::{Field, Integrator, IntegratorKind};
+use crate::oct_tree::{Dimensions, OctTree, OctTreeField};
+use crate::parallel::Walker;
 use crate::quad_tree::QuadTree;
-use crate::vector::Vector;
-
-pub const THETA: f32 = 0.5;
+use crate::solver::{DirectSum, FastMultipoleField, QuadTreeField, Solver};
+use crate::system::{FORCE_THREADS, OPENING_CRITERION};
+use crate::timestep::FixedTimestep;
+use crate::float::Real;
 
 #[derive(SystemDesc)]
-pub struct BodyForceSystem;
+pub struct BodyForceSystem {
+  integrator: Box<dyn Integrator>,
+  // Only used in 2D, 3D runs always walk the oct tree
+  solver: Solver,
+  dimensions: Dimensions,
+  walker: Walker,
+  // Whether the bodies hold accelerations for integrators that reuse them
+  primed: bool,
+}
+
+impl BodyForceSystem {
+  pub fn new(integrator: IntegratorKind, solver: Solver, dimensions: Dimensions) -> Self {
+    BodyForceSystem {
+      integrator: integrator.create(),
+      solver,
+      dimensions,
+      walker: Walker::new(FORCE_THREADS),
+      primed: false,
+    }
+  }
+
+  // `prime` is set on the first step, for integrators that need the accelerations before it
+  fn step(&self, bodies: &mut WriteStorage<Body>, field: &mut dyn Field, time_step: Real, prime: bool) {
+    let mut snapshot: Vec<Body> = (&*bodies).join().cloned().collect();
+    snapshot.iter_mut().for_each(|body| body.store_previous_coordinate());
+    let reuses_accelerations = self.integrator.reuses_accelerations();
+    if reuses_accelerations && prime {
+      self.integrator.prime(&mut snapshot, field, time_step);
+    }
+
+    self.integrator.step(&mut snapshot, field, time_step);
+
+    for (body, mut next) in (bodies).join().zip(snapshot) {
+      debug_assert_eq!(body.get_id(), next.get_id(), "snapshot is out of order");
+      // Otherwise the integrator left the acceleration at the end of the step
+      if !reuses_accelerations {
+        next.set_acceleration((*next.get_velocity() - *body.get_velocity()) / time_step);
+      }
+      *body = next;
+    }
+  }
+}
 
 impl<'s> System<'s> for BodyForceSystem {
   type SystemData = (
-    ReadStorage<'s, QuadTree>,
+    WriteStorage<'s, QuadTree>,
+    WriteStorage<'s, OctTree>,
     WriteStorage<'s, Body>,
-    WriteStorage<'s, Transform>,
-    Read<'s, Time>,
+    ReadExpect<'s, FixedTimestep>,
   );
 
-  fn run(&mut self, (quad_trees, mut bodies, mut transforms, time): Self::SystemData) {
-    for quad_tree in (&quad_trees).join() {
+  fn run(&mut self, (mut quad_trees, mut oct_trees, mut bodies, timestep): Self::SystemData) {
+    if let Dimensions::Three { theta } = self.dimensions {
+      for oct_tree in (&mut oct_trees).join() {
+        let field = &mut OctTreeField::new(oct_tree, theta, &self.walker);
+        self.step(&mut bodies, field, timestep.get_time_step(), !self.primed);
+        self.primed = true;
+      }
+      return;
+    }
 
-    for (body, transform) in (&mut bodies, &mut transforms).join() {
-        let force = quad_tree.calculate_net_force_on(&body, THETA);
-        let mass = body.get_mass();
-        let accelaration = Vector::new(force.get_x()/mass, force.get_y()/mass);
-        let delta = body.update_coordinate(accelaration, time.delta_seconds());
-        body.update_velocity(accelaration, time.delta_seconds());
+    for quad_tree in (&mut quad_trees).join() {
+      let mut field: Box<dyn Field> = match self.solver {
+        Solver::BarnesHut => Box::new(QuadTreeField::new(quad_tree, OPENING_CRITERION, &self.walker)),
+        Solver::Direct => Box::new(DirectSum),
+        Solver::FastMultipole { order, theta } => {
+          Box::new(FastMultipoleField::new(quad_tree, order, theta, OPENING_CRITERION))
+        }
+      };
+      self.step(&mut bodies, field.as_mut(), timestep.get_time_step(), !self.primed);
+      drop(field);
+      self.primed = true;
 
-        transform.prepend_translation_x(delta.get_x());
-        transform.prepend_translation_y(delta.get_y());
+      if let Some(domain) = quad_tree.get_domain() {
+        for body in (&mut bodies).join() {
+          domain.wrap(body);
+        }
       }
     }
   }
 }
-
@@ -0,0 +1,67 @@
+use amethyst::{
+  core::{math::Vector3, transform::Transform, SystemDesc},
+  derive::SystemDesc,
+  ecs::prelude::{Entities, Entity, Join, ReadStorage, System, SystemData, World, Write, WriteStorage},
+};
+
+use crate::body::{selection::Selection, Body};
+use crate::collision::find_overlapping_pairs;
+use crate::quad_tree::QuadTree;
+use crate::system::{get_scale_factor, SPRITE_RADIUS};
+use crate::float::to_f32;
+
+// Merges overlapping bodies. The surviving entity takes over the mass, momentum and centre of mass
+// of the bodies it absorbs, which are deleted from the world, and the selection when one of them had it.
+#[derive(SystemDesc)]
+pub struct BodyMergeSystem;
+
+impl<'s> System<'s> for BodyMergeSystem {
+  type SystemData = (
+    Entities<'s>,
+    ReadStorage<'s, QuadTree>,
+    WriteStorage<'s, Body>,
+    WriteStorage<'s, Transform>,
+    Option<Write<'s, Selection>>,
+  );
+
+  fn run(&mut self, (entities, quad_trees, mut bodies, mut transforms, mut selection): Self::SystemData) {
+    for quad_tree in (&quad_trees).join() {
+      let (entity_list, mut snapshot): (Vec<Entity>, Vec<Body>) = (&entities, &bodies).join()
+        .map(|(entity, body)| (entity, *body))
+        .unzip();
+      let mut is_absorbed = vec![false; snapshot.len()];
+      let mut has_merged = vec![false; snapshot.len()];
+
+      for (i, j) in find_overlapping_pairs(quad_tree, &snapshot) {
+        // Earlier merges in this step may have moved the survivor away
+        if is_absorbed[i] || is_absorbed[j]
+          || snapshot[i].get_distance_between(&snapshot[j]) >= snapshot[i].get_radius() + snapshot[j].get_radius()
+        {
+          continue;
+        }
+
+        let absorbed = snapshot[j];
+        snapshot[i].absorb(&absorbed);
+        if let Some(selection) = selection.as_mut() {
+          selection.on_absorbed(absorbed.get_id(), snapshot[i].get_id());
+        }
+        is_absorbed[j] = true;
+        has_merged[i] = true;
+      }
+
+      for (i, entity) in entity_list.into_iter().enumerate() {
+        if is_absorbed[i] {
+          entities.delete(entity).expect("absorbed body was already deleted");
+        } else if has_merged[i] {
+          let scale_factor = get_scale_factor(snapshot[i].get_mass());
+          snapshot[i].set_radius(SPRITE_RADIUS * scale_factor);
+          *bodies.get_mut(entity).unwrap() = snapshot[i];
+
+          if let Some(transform) = transforms.get_mut(entity) {
+            transform.set_scale(Vector3::new(to_f32(scale_factor), to_f32(scale_factor), 0.0));
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,27 @@
+use amethyst::{
+  derive::SystemDesc,
+  ecs::prelude::{Join, ReadStorage, System, SystemData, WriteStorage},
+};
+
+use crate::body::Body;
+use crate::oct_tree::OctTree;
+
+#[derive(SystemDesc)]
+pub struct BodyOctTreeSystem;
+
+impl<'s> System<'s> for BodyOctTreeSystem {
+  type SystemData = (
+    WriteStorage<'s, OctTree>,
+    ReadStorage<'s, Body>,
+  );
+
+  fn run(&mut self, (mut oct_trees, bodies): Self::SystemData) {
+    for oct_tree in (&mut oct_trees).join() {
+      oct_tree.reset_root();
+
+      for body in (&bodies).join() {
+        oct_tree.insert(*body);
+      }
+    }
+  }
+}
@@ -1,13 +1,23 @@
 use amethyst::{
+  core::SystemDesc,
   derive::SystemDesc,
-  ecs::prelude::{Join, ReadStorage, System, SystemData, WriteStorage},
+  ecs::prelude::{Join, ReadStorage, System, SystemData, World, WriteStorage},
 };
 
 use crate::body::Body;
 use crate::quad_tree::QuadTree;
 
 #[derive(SystemDesc)]
-pub struct BodyQuadTreeSystem;
+pub struct BodyQuadTreeSystem {
+  // Physics steps so far, which decide when `REBUILD_INTERVAL` asks for a rebuild
+  steps: usize,
+}
+
+impl BodyQuadTreeSystem {
+  pub fn new() -> Self {
+    BodyQuadTreeSystem { steps: 0 }
+  }
+}
 
 impl<'s> System<'s> for BodyQuadTreeSystem {
   type SystemData = (
@@ -16,12 +26,12 @@ impl<'s> System<'s> for BodyQuadTreeSystem {
   );
 
   fn run(&mut self, (mut quad_trees, bodies): Self::SystemData) {
-    for quad_tree in (&mut quad_trees).join() {
-      quad_tree.reset_root();
+    let bodies: Vec<Body> = (&bodies).join().cloned().collect();
 
-      for body in (&bodies).join() {
-        quad_tree.insert(*body);
-      }
+    for quad_tree in (&mut quad_trees).join() {
+      // Rebuilt around the bodies, or refitted to them between rebuilds when `REBUILD_INTERVAL` is set
+      quad_tree.update(&bodies, self.steps);
     }
+    self.steps += 1;
   }
 }
@@ -0,0 +1,67 @@
+use amethyst::{
+  core::{math::Vector3, transform::Transform, SystemDesc},
+  derive::SystemDesc,
+  ecs::prelude::{Join, Read, ReadExpect, ReadStorage, System, SystemData, World, WriteStorage},
+};
+
+use crate::body::{coordinate::Coordinate, Body};
+use crate::camera::OrbitCamera;
+use crate::float::{to_f32, Real};
+use crate::system::{get_scale_factor, ARENA_LENGTH};
+use crate::timestep::FixedTimestep;
+
+// Places every rendered body between its last two physics states, projected through the
+// `OrbitCamera` in the 3D mode. Interpolation runs in the simulation's precision, the transform gets f32.
+#[derive(SystemDesc)]
+pub struct BodyTransformSystem;
+
+// Sprites further along the view are drawn behind nearer ones by their z, which has to stay between
+// the 2D camera's planes
+const DEPTH_RANGE: Real = 1000.0;
+
+// Maps a projected depth into (-DEPTH_RANGE, 0), larger depths further back
+fn get_depth_z(depth: Real) -> Real {
+  -DEPTH_RANGE * depth / (depth + ARENA_LENGTH)
+}
+
+impl<'s> System<'s> for BodyTransformSystem {
+  type SystemData = (
+    ReadStorage<'s, Body>,
+    WriteStorage<'s, Transform>,
+    ReadExpect<'s, FixedTimestep>,
+    Option<Read<'s, OrbitCamera>>,
+  );
+
+  fn run(&mut self, (bodies, mut transforms, timestep, camera): Self::SystemData) {
+    let alpha = timestep.get_alpha();
+
+    for (body, transform) in (&bodies, &mut transforms).join() {
+      let previous = body.get_previous_coordinate();
+      let current = body.get_coordinate();
+      let interpolate = |previous: Real, current: Real| previous + (current - previous) * alpha;
+
+      match &camera {
+        Some(camera) => {
+          let coordinate = Coordinate::new_3d(
+            interpolate(previous.get_x(), current.get_x()),
+            interpolate(previous.get_y(), current.get_y()),
+            interpolate(previous.get_z(), current.get_z()),
+          );
+          // Bodies behind the camera shrink out of sight
+          let (x, y, z, scale) = match camera.project(&coordinate) {
+            Some(projection) => (projection.x, projection.y, get_depth_z(projection.depth), projection.scale),
+            None => (coordinate.get_x(), coordinate.get_y(), -DEPTH_RANGE, 0.0),
+          };
+          let scale = get_scale_factor(body.get_mass()) * scale;
+
+          transform.set_translation_xyz(to_f32(x), to_f32(y), to_f32(z));
+          transform.set_scale(Vector3::new(to_f32(scale), to_f32(scale), 0.0));
+        }
+        None => {
+          transform.set_translation_x(to_f32(interpolate(previous.get_x(), current.get_x())));
+          transform.set_translation_y(to_f32(interpolate(previous.get_y(), current.get_y())));
+        }
+      }
+    }
+  }
+}
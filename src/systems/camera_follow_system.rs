@@ -0,0 +1,59 @@
+use amethyst::{
+  core::{transform::Transform, SystemDesc},
+  derive::SystemDesc,
+  ecs::prelude::{Join, Read, ReadStorage, System, SystemData, World, Write, WriteStorage},
+  renderer::Camera,
+};
+
+use crate::body::{Body, coordinate::Coordinate, selection::Selection};
+use crate::camera::OrbitCamera;
+use crate::system::CAMERA_FOLLOW;
+use crate::float::to_f32;
+
+// Centres the camera, and the 3D camera's target, on the selected body, or the bodies' centre of mass
+// when none is, when CAMERA_FOLLOW is set
+#[derive(SystemDesc)]
+pub struct CameraFollowSystem;
+
+impl<'s> System<'s> for CameraFollowSystem {
+  type SystemData = (
+    ReadStorage<'s, Body>,
+    ReadStorage<'s, Camera>,
+    WriteStorage<'s, Transform>,
+    Option<Write<'s, OrbitCamera>>,
+    Option<Read<'s, Selection>>,
+  );
+
+  fn run(&mut self, (bodies, cameras, mut transforms, orbit_camera, selection): Self::SystemData) {
+    if !CAMERA_FOLLOW {
+      return;
+    }
+
+    let selected = selection.as_ref().and_then(|selection| selection.find((&bodies).join()));
+    let centre = match selected {
+      Some(body) => *body.get_coordinate(),
+      None => {
+        let (mut mass, mut x, mut y, mut z) = (0.0, 0.0, 0.0, 0.0);
+        for body in (&bodies).join() {
+          let coordinate = body.get_coordinate();
+          mass += body.get_mass();
+          x += coordinate.get_x() * body.get_mass();
+          y += coordinate.get_y() * body.get_mass();
+          z += coordinate.get_z() * body.get_mass();
+        }
+        if mass == 0.0 {
+          return;
+        }
+        Coordinate::new_3d(x / mass, y / mass, z / mass)
+      }
+    };
+
+    for (_, transform) in (&cameras, &mut transforms).join() {
+      transform.set_translation_x(to_f32(centre.get_x()));
+      transform.set_translation_y(to_f32(centre.get_y()));
+    }
+    if let Some(mut orbit_camera) = orbit_camera {
+      orbit_camera.set_target(centre);
+    }
+  }
+}
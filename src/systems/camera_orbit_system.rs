@@ -0,0 +1,39 @@
+use amethyst::{
+  core::{timing::Time, SystemDesc},
+  derive::SystemDesc,
+  ecs::prelude::{Read, System, SystemData, World, Write},
+  input::{InputHandler, StringBindings, VirtualKeyCode},
+};
+
+use crate::camera::OrbitCamera;
+use crate::float::Real;
+use crate::system::{ORBIT_SPEED, ZOOM_SPEED};
+
+// Turns the 3D camera with the arrow keys and zooms with W and S; does nothing in the 2D mode
+#[derive(SystemDesc)]
+pub struct CameraOrbitSystem;
+
+impl<'s> System<'s> for CameraOrbitSystem {
+  type SystemData = (
+    Option<Write<'s, OrbitCamera>>,
+    Read<'s, InputHandler<StringBindings>>,
+    Read<'s, Time>,
+  );
+
+  fn run(&mut self, (camera, input, time): Self::SystemData) {
+    let mut camera = match camera {
+      Some(camera) => camera,
+      None => return,
+    };
+    let elapsed = time.delta_seconds() as Real;
+    let axis = |negative, positive| {
+      (input.key_is_down(positive) as i32 - input.key_is_down(negative) as i32) as Real
+    };
+
+    camera.rotate(
+      axis(VirtualKeyCode::Left, VirtualKeyCode::Right) * ORBIT_SPEED * elapsed,
+      axis(VirtualKeyCode::Down, VirtualKeyCode::Up) * ORBIT_SPEED * elapsed,
+    );
+    camera.zoom((-axis(VirtualKeyCode::S, VirtualKeyCode::W) * ZOOM_SPEED * elapsed).exp());
+  }
+}
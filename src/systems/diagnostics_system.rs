@@ -0,0 +1,52 @@
+use amethyst::{
+  core::SystemDesc,
+  derive::SystemDesc,
+  ecs::prelude::{Join, ReadStorage, System, SystemData, World, WriteExpect},
+};
+use log::info;
+
+use crate::body::Body;
+use crate::diagnostics::{Diagnostics, Sample};
+use crate::quad_tree::QuadTree;
+use crate::system::OPENING_CRITERION;
+
+// Samples the conserved quantities every `Diagnostics` interval
+#[derive(SystemDesc)]
+pub struct DiagnosticsSystem;
+
+impl<'s> System<'s> for DiagnosticsSystem {
+  type SystemData = (
+    ReadStorage<'s, QuadTree>,
+    ReadStorage<'s, Body>,
+    WriteExpect<'s, Diagnostics>,
+  );
+
+  fn run(&mut self, (quad_trees, bodies, mut diagnostics): Self::SystemData) {
+    if !diagnostics.next_step() {
+      return;
+    }
+
+    for quad_tree in (&quad_trees).join() {
+      // The shared tree still holds the bodies from before they last moved
+      let snapshot: Vec<Body> = (&bodies).join().cloned().collect();
+      let mut current_tree = quad_tree.new_like();
+      current_tree.rebuild(&snapshot);
+
+      let sample = Sample::measure(diagnostics.get_step(), &snapshot, &current_tree, OPENING_CRITERION);
+      diagnostics.record(sample);
+      info!(
+        "step {}: energy {} (kinetic {}, potential {}, relative drift {}), momentum ({}, {}), angular momentum {}, centre of mass ({}, {})",
+        sample.step,
+        sample.get_total_energy(),
+        sample.kinetic_energy,
+        sample.potential_energy,
+        diagnostics.get_energy_error().unwrap_or(0.0),
+        sample.momentum.get_x(),
+        sample.momentum.get_y(),
+        sample.angular_momentum,
+        sample.centre_of_mass.get_x(),
+        sample.centre_of_mass.get_y(),
+      );
+    }
+  }
+}
@@ -0,0 +1,56 @@
+use amethyst::{
+  core::SystemDesc,
+  derive::SystemDesc,
+  ecs::prelude::{Join, ReadStorage, System, SystemData, World, Write},
+};
+use log::info;
+
+use crate::body::Body;
+use crate::quad_tree::QuadTree;
+use crate::solver::{compare_forces, ForceErrors};
+use crate::system::OPENING_CRITERION;
+
+// Every `interval` steps, measures how far the tree forces are from the exact direct sum
+#[derive(SystemDesc)]
+pub struct ForceComparisonSystem {
+  interval: u64,
+  #[system_desc(skip)]
+  step: u64,
+}
+
+impl ForceComparisonSystem {
+  pub fn new(interval: u64) -> Self {
+    ForceComparisonSystem { interval, step: 0 }
+  }
+}
+
+impl<'s> System<'s> for ForceComparisonSystem {
+  type SystemData = (
+    ReadStorage<'s, QuadTree>,
+    ReadStorage<'s, Body>,
+    Write<'s, ForceErrors>,
+  );
+
+  fn run(&mut self, (quad_trees, bodies, mut force_errors): Self::SystemData) {
+    self.step += 1;
+    if self.step % self.interval != 0 {
+      return;
+    }
+
+    for quad_tree in (&quad_trees).join() {
+      let snapshot: Vec<Body> = (&bodies).join().cloned().collect();
+      let mut current_tree = quad_tree.new_like();
+      current_tree.rebuild(&snapshot);
+
+      force_errors.update(&snapshot, compare_forces(&current_tree, &snapshot, OPENING_CRITERION));
+      info!(
+        "step {}: relative force error with {:?}: mean {}, 99th percentile {}, max {}",
+        self.step,
+        OPENING_CRITERION,
+        force_errors.get_mean(),
+        force_errors.get_percentile_99(),
+        force_errors.get_max(),
+      );
+    }
+  }
+}
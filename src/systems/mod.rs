@@ -1,7 +1,25 @@
+mod body_bounce_system;
+mod body_boundary_system;
 mod body_force_system;
+mod body_merge_system;
+mod body_oct_tree_system;
 mod body_quad_tree_system;
+mod body_transform_system;
+mod camera_follow_system;
+mod camera_orbit_system;
+mod diagnostics_system;
+mod force_comparison_system;
 
 pub use self::{
+  body_bounce_system::BodyBounceSystem,
+  body_boundary_system::BodyBoundarySystem,
   body_force_system::BodyForceSystem,
+  body_merge_system::BodyMergeSystem,
+  body_oct_tree_system::BodyOctTreeSystem,
   body_quad_tree_system::BodyQuadTreeSystem,
-};
\ No newline at end of file
+  body_transform_system::BodyTransformSystem,
+  camera_follow_system::CameraFollowSystem,
+  camera_orbit_system::CameraOrbitSystem,
+  diagnostics_system::DiagnosticsSystem,
+  force_comparison_system::ForceComparisonSystem,
+};
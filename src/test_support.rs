@@ -0,0 +1,13 @@
+use crate::body::{coordinate::Coordinate, Body};
+use crate::float::Real;
+
+// Bodies on a sunflower spiral filling a disc of radius 400 around (500, 500)
+pub fn get_spiral(count: usize) -> Vec<Body> {
+  (0..count)
+    .map(|i| {
+      let angle = i as Real * 2.39;
+      let radius = 400.0 * (i as Real / count as Real).sqrt();
+      Body::new(1.0 + (i % 3) as Real, Coordinate::new(500.0 + radius * angle.cos(), 500.0 + radius * angle.sin()))
+    })
+    .collect()
+}
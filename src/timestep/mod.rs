@@ -0,0 +1,70 @@
+use crate::float::Real;
+
+// Accumulates rendered frame time and converts it into a whole number of fixed physics steps,
+// so that the simulation does not depend on the frame rate. Time is kept in `Real` so that long runs
+// in f64 don't drift; only the frame delta comes in as f32.
+#[derive(Debug)]
+pub struct FixedTimestep {
+  time_step: Real,
+  max_substeps: u32,
+  accumulator: Real,
+}
+
+impl FixedTimestep {
+  pub fn new(time_step: Real, max_substeps: u32) -> Self {
+    FixedTimestep {
+      time_step,
+      max_substeps,
+      accumulator: 0.0,
+    }
+  }
+
+  pub fn get_time_step(&self) -> Real {
+    self.time_step
+  }
+
+  // Returns the number of physics steps to run for a frame that took `elapsed` seconds.
+  // Anything beyond `max_substeps` is dropped, so a stutter slows the simulation down instead of
+  // taking a giant step or spiralling into ever longer frames.
+  pub fn advance(&mut self, elapsed: f32) -> u32 {
+    self.accumulator += Real::from(elapsed);
+    let steps = (self.accumulator / self.time_step).floor() as u32;
+
+    if steps > self.max_substeps {
+      self.accumulator = 0.0;
+      return self.max_substeps;
+    }
+
+    self.accumulator -= steps as Real * self.time_step;
+    steps
+  }
+
+  // How far rendering is between the previous and the current physics state, in [0, 1)
+  pub fn get_alpha(&self) -> Real {
+    self.accumulator / self.time_step
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::FixedTimestep;
+
+  #[test]
+  fn test_advance() {
+    let mut timestep = FixedTimestep::new(0.01, 5);
+
+    assert_eq!(timestep.advance(0.025), 2);
+    assert!((timestep.get_alpha() - 0.5).abs() < 1e-3);
+    assert_eq!(timestep.advance(0.006), 1);
+    assert!((timestep.get_alpha() - 0.1).abs() < 1e-3);
+  }
+
+  #[test]
+  fn test_advance_caps_substeps() {
+    let mut timestep = FixedTimestep::new(0.01, 5);
+
+    assert_eq!(timestep.advance(1.0), 5);
+    assert_eq!(timestep.get_alpha(), 0.0);
+    assert_eq!(timestep.advance(0.0), 0);
+  }
+}
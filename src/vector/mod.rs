@@ -1,42 +1,129 @@
-use std::ops::Add;
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+use crate::float::{Float, Real};
 
 #[derive(Debug, Clone, Copy)]
-pub struct Vector {
-  x: f32,
-  y: f32,
+pub struct Vector<T: Float = Real> {
+  x: T,
+  y: T,
+  // Always 0 in the 2D simulation
+  z: T,
 }
 
-impl Vector {
-  pub fn new(x: f32, y: f32) -> Self {
-    Vector {x, y}
+impl<T: Float> Vector<T> {
+  pub fn new(x: T, y: T) -> Self {
+    Vector {x, y, z: T::ZERO}
   }
 
-  pub fn new_with(magnitude: f32, angle: f32, direction: Self) -> Self {
-    let x = magnitude * angle.cos() * direction.x;
-    let y = magnitude * angle.sin() * direction.y;
-
-    Vector {x, y}
+  pub fn new_3d(x: T, y: T, z: T) -> Self {
+    Vector {x, y, z}
   }
 
-  pub fn get_x(&self) -> f32 {
+  pub fn get_x(&self) -> T {
     self.x
   }
 
-  pub fn get_y(&self) -> f32 {
+  pub fn get_y(&self) -> T {
     self.y
   }
+
+  pub fn get_z(&self) -> T {
+    self.z
+  }
+
+  pub fn dot(&self, other: &Self) -> T {
+    self.x * other.x + self.y * other.y + self.z * other.z
+  }
+
+  pub fn get_norm(&self) -> T {
+    self.dot(self).sqrt()
+  }
+
+  // Unit vector in the same direction, the zero vector stays zero
+  pub fn normalise(&self) -> Self {
+    let norm = self.get_norm();
+    if norm == T::ZERO {
+      return *self;
+    }
+
+    *self / norm
+  }
+}
+
+impl<T: Float> Add for Vector<T> {
+  type Output = Vector<T>;
+
+  fn add(self, rhs: Vector<T>) -> Vector<T> {
+      Vector::new_3d(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+  }
+}
+
+impl<T: Float> AddAssign for Vector<T> {
+  fn add_assign(&mut self, rhs: Vector<T>) {
+      *self = *self + rhs;
+  }
+}
+
+impl<T: Float> Sub for Vector<T> {
+  type Output = Vector<T>;
+
+  fn sub(self, rhs: Vector<T>) -> Vector<T> {
+      Vector::new_3d(self.x - rhs.x, self.y - rhs.y, self.z - rhs.z)
+  }
+}
+
+impl<T: Float> SubAssign for Vector<T> {
+  fn sub_assign(&mut self, rhs: Vector<T>) {
+      *self = *self - rhs;
+  }
+}
+
+impl<T: Float> Mul<T> for Vector<T> {
+  type Output = Vector<T>;
+
+  fn mul(self, rhs: T) -> Vector<T> {
+      Vector::new_3d(self.x * rhs, self.y * rhs, self.z * rhs)
+  }
+}
+
+impl<T: Float> Div<T> for Vector<T> {
+  type Output = Vector<T>;
+
+  fn div(self, rhs: T) -> Vector<T> {
+      Vector::new_3d(self.x / rhs, self.y / rhs, self.z / rhs)
+  }
 }
 
-impl Add for Vector {
-  type Output = Vector;
+impl<T: Float> Neg for Vector<T> {
+  type Output = Vector<T>;
 
-  fn add(self, rhs: Vector) -> Vector {
-      Vector::new(self.x + rhs.x, self.y + rhs.y)
+  fn neg(self) -> Vector<T> {
+      Vector::new_3d(-self.x, -self.y, -self.z)
   }
 }
 
-impl PartialEq for Vector {
+impl<T: Float> PartialEq for Vector<T> {
   fn eq(&self, other: &Self) -> bool {
-      self.x == other.x && self.y == other.y
+      self.x == other.x && self.y == other.y && self.z == other.z
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::Vector;
+
+  #[test]
+  fn test_algebra() {
+    let mut a = Vector::new_3d(3.0, 0.0, 4.0);
+    let b = Vector::new(1.0, 2.0);
+
+    assert_eq!(a.dot(&b), 3.0);
+    assert_eq!(a.get_norm(), 5.0);
+    assert_eq!(a.normalise(), Vector::new_3d(0.6, 0.0, 0.8));
+    assert_eq!(-(a - b) / 2.0, Vector::new_3d(-1.0, 1.0, -2.0));
+
+    a += b;
+    assert_eq!(a, Vector::new_3d(4.0, 2.0, 4.0));
+    assert_eq!(Vector::new(0.0, 0.0).normalise(), Vector::new(0.0, 0.0));
   }
-}
\ No newline at end of file
+}